@@ -1,10 +1,16 @@
 use std::{
+    collections::HashMap,
     fs, io,
     path::{Path, PathBuf},
 };
 
 use color_eyre::eyre::{anyhow, Context};
-use serde_json::Value;
+use serde_json::{Map, Value};
+
+/// Section of environment files with variables of every environment
+const SHARED_ENVIRONMENT: &str = "$shared";
+/// Key of the snapshot file holding snapshots of every environment by its name
+const SNAPSHOT_ENVIRONMENTS: &str = "$environments";
 
 pub trait EnvironmentProvider {
     fn snapshot(&self) -> Value;
@@ -48,40 +54,101 @@ impl EnvironmentProvider for StaticEnvironmentProvider {
 #[derive(Debug, Clone)]
 pub struct EnvironmentFileProvider {
     snapshot: serde_json::Map<String, Value>,
+    environment_name: String,
+    /// Snapshots of every environment, the selected one is replaced on save
+    snapshots: Map<String, Value>,
     snapshot_path: PathBuf,
+    /// Variables overriding the environment files, they are not saved unless changed
+    overrides: HashMap<String, Override>,
+}
+
+#[derive(Debug, Clone)]
+struct Override {
+    value: Value,
+    /// Value of the variable before it was overridden, which is saved instead of the override
+    previous: Option<Value>,
 }
 
 impl EnvironmentFileProvider {
+    /// Variables are taken from the `$shared` sections, overridden by the selected environment,
+    /// of the environment file and then of the private one, and all of them override the snapshot.
+    /// Variables of the private file are not saved to the snapshot, unless a script changes them.
     pub fn open(
         environment_name: &str,
         environment_path: impl AsRef<Path>,
+        private_environment_path: impl AsRef<Path>,
         snapshot_path: impl AsRef<Path>,
     ) -> crate::Result<Self> {
-        let Value::Object(mut environment) =
-            read_json_content(environment_path.as_ref()).context("environment deserialization")?
-        else {
-            return Err(anyhow!("Expected environment file to be a map"));
-        };
-
-        let Value::Object(mut environment) = environment
-            .remove(environment_name)
-            .unwrap_or_else(|| serde_json::json!({}))
-        else {
-            return Err(anyhow!("Expected selected environment to be a map"));
-        };
+        let mut environments =
+            read_environments(environment_path.as_ref()).context("environment deserialization")?;
+        let mut private_environments = read_environments(private_environment_path.as_ref())
+            .context("private environment deserialization")?;
+
+        let mut environment = Map::new();
+        let mut private = Map::new();
+        for name in [SHARED_ENVIRONMENT, environment_name] {
+            for (key, value) in environments.section(name)? {
+                private.remove(&key);
+                environment.insert(key, value);
+            }
+            private.append(&mut private_environments.section(name)?);
+        }
 
-        let Value::Object(mut snapshot) =
-            read_json_content(snapshot_path.as_ref()).context("snapshot deserialization")?
-        else {
-            return Err(anyhow!("Expected snapshot file to be a map"));
+        let snapshots = read_snapshots(snapshot_path.as_ref(), environment_name)
+            .context("snapshot deserialization")?;
+        let mut snapshot = match snapshots.get(environment_name) {
+            None => Map::new(),
+            Some(Value::Object(snapshot)) => snapshot.clone(),
+            Some(_) => {
+                return Err(anyhow!(
+                    "Expected snapshot of `{environment_name}` environment to be a map"
+                ))
+            }
         };
 
         snapshot.append(&mut environment);
 
         Ok(Self {
             snapshot,
+            environment_name: environment_name.to_owned(),
+            snapshots,
             snapshot_path: snapshot_path.as_ref().to_owned(),
-        })
+            overrides: HashMap::new(),
+        }
+        .with_variables(private))
+    }
+
+    /// Overrides variables of the environment files, e.g. with the ones given on the command line.
+    /// Such variables are not saved to the snapshot, unless a script changes them.
+    pub fn with_variables(mut self, variables: Map<String, Value>) -> Self {
+        for (name, value) in variables {
+            let previous = self.snapshot.insert(name.clone(), value.clone());
+            self.overrides
+                .entry(name)
+                .and_modify(|it| it.value = value.clone())
+                .or_insert(Override { value, previous });
+        }
+        self
+    }
+
+    /// Private environment file next to the environment one, the way IntelliJ names it:
+    /// `http-client.env.json` has `http-client.private.env.json`
+    pub fn private_environment_path(environment_path: impl AsRef<Path>) -> PathBuf {
+        let path = environment_path.as_ref();
+        let name = path
+            .file_name()
+            .map(|it| it.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let private_name = if let Some(stem) = name.strip_suffix(".env.json") {
+            format!("{stem}.private.env.json")
+        } else if let Some((stem, extension)) = name.rsplit_once('.') {
+            format!("{stem}.private.{extension}")
+        } else {
+            format!("{name}.private")
+        };
+
+        path.with_file_name(private_name)
     }
 }
 
@@ -91,12 +158,76 @@ impl EnvironmentProvider for EnvironmentFileProvider {
     }
 
     fn save(&mut self, snapshot: &Value) -> io::Result<()> {
-        fs::write(&self.snapshot_path, serde_json::to_string_pretty(snapshot)?)?;
+        let mut snapshot = snapshot.clone();
+        if let Value::Object(variables) = &mut snapshot {
+            for (name, Override { value, previous }) in &self.overrides {
+                // a variable with another value was set by a script
+                if variables.get(name) != Some(value) {
+                    continue;
+                }
+                match previous {
+                    Some(previous) => variables.insert(name.clone(), previous.clone()),
+                    None => variables.remove(name),
+                };
+            }
+        }
+
+        self.snapshots
+            .insert(self.environment_name.clone(), snapshot);
+        let file = serde_json::json!({ SNAPSHOT_ENVIRONMENTS: self.snapshots });
+        fs::write(&self.snapshot_path, serde_json::to_string_pretty(&file)?)?;
 
         Ok(())
     }
 }
 
+/// Environments of a file, none if the file does not exist
+struct Environments<'a> {
+    path: &'a Path,
+    environments: Map<String, Value>,
+}
+
+fn read_environments(path: &Path) -> crate::Result<Environments<'_>> {
+    let Value::Object(environments) = read_json_content(path)? else {
+        return Err(anyhow!("Expected `{}` to be a map", path.display()));
+    };
+
+    Ok(Environments { path, environments })
+}
+
+impl Environments<'_> {
+    fn section(&mut self, name: &str) -> crate::Result<Map<String, Value>> {
+        match self.environments.remove(name) {
+            None => Ok(Map::new()),
+            Some(Value::Object(environment)) => Ok(environment),
+            Some(_) => Err(anyhow!(
+                "Expected `{name}` environment of `{}` to be a map",
+                self.path.display()
+            )),
+        }
+    }
+}
+
+/// Snapshots of every environment by its name.
+/// Snapshots used to be shared by all environments, such a snapshot becomes the selected one's.
+fn read_snapshots(path: &Path, environment_name: &str) -> crate::Result<Map<String, Value>> {
+    let Value::Object(mut snapshot) = read_json_content(path)? else {
+        return Err(anyhow!("Expected snapshot file to be a map"));
+    };
+
+    match snapshot.remove(SNAPSHOT_ENVIRONMENTS) {
+        Some(Value::Object(snapshots)) => Ok(snapshots),
+        Some(_) => Err(anyhow!(
+            "Expected `{SNAPSHOT_ENVIRONMENTS}` of the snapshot to be a map"
+        )),
+        None if snapshot.is_empty() => Ok(Map::new()),
+        None => Ok(Map::from_iter([(
+            environment_name.to_owned(),
+            Value::Object(snapshot),
+        )])),
+    }
+}
+
 fn read_json_content(path: &Path) -> crate::Result<Value> {
     match fs::read(path) {
         Ok(data) => Ok(serde_json::from_slice(&data).context("json deserialization")?),
@@ -1,33 +1,102 @@
-use color_eyre::eyre::Context;
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use color_eyre::eyre::{anyhow, Context};
 
 use crate::{
-    http::{HttpClient, Request, Response},
+    http::{
+        Body, Direction, DuplexConnection, HttpClient, Message, MessageStream, Method, Part,
+        Request, RequestOptions, Response,
+    },
     output::Output,
-    parser::{self, Header},
-    script_engine::{self, report::TestsReport, ScriptEngine},
+    parser::{self, multipart, Header},
+    script_engine::{self, escaping::Escaping, report::TestsReport, ScriptEngine},
     source::SourceItem,
     Result,
 };
 
+const PROTO_FILES_VARIABLE: &str = "proto_files";
+/// How long `=== wait-for-server` waits for a message when the request has no `@timeout`
+const WAIT_FOR_SERVER_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub(crate) struct Executor<'a> {
     source: SourceItem<'a>,
+    /// Elements of the array variables used by this execution of the request
+    iteration: Vec<(String, String)>,
 }
 
 impl<'a> Executor<'a> {
-    pub(crate) fn new(source: SourceItem<'a>) -> Self {
-        Self { source }
+    /// Executors for every combination of elements of the array variables the request uses,
+    /// or a single one if it uses none
+    pub(crate) fn iterations(
+        source: SourceItem<'a>,
+        engine: &mut impl ScriptEngine,
+    ) -> Result<Vec<Self>> {
+        let script = source.script;
+        let mut names: Vec<&str> = vec![];
+        for inline_script in script.inline_scripts() {
+            let name = inline_script.script.trim();
+            // request variables take precedence over global and environment ones
+            let is_request_variable = script
+                .request_variables
+                .iter()
+                .chain(source.overrides)
+                .any(|(it, _)| it == name);
+            if inline_script.is_variable() && !is_request_variable && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        let mut iterations = vec![vec![]];
+        for name in names {
+            let Some(values) = engine.collection(name)? else {
+                continue;
+            };
+
+            let mut combinations = vec![];
+            for iteration in &iterations {
+                for value in &values {
+                    let mut combination: Vec<(String, String)> = iteration.clone();
+                    combination.push((name.to_owned(), value.clone()));
+                    combinations.push(combination);
+                }
+            }
+            iterations = combinations;
+        }
+
+        Ok(iterations
+            .into_iter()
+            .map(|iteration| Self { source, iteration })
+            .collect())
+    }
+
+    /// Name of the request, followed by the elements used if it is iterated
+    pub(crate) fn name(&self) -> String {
+        let name = self.source.request_name();
+        if self.iteration.is_empty() {
+            return name;
+        }
+
+        let elements = self
+            .iteration
+            .iter()
+            .map(|(variable, value)| format!("{variable}={value}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{name} [{elements}]")
     }
 
     fn request_name(&self) -> String {
-        format!(
-            "{} / {}",
-            self.source.source_name(),
-            self.source.request_name()
-        )
+        format!("{} / {}", self.source.source_name(), self.name())
     }
 
     fn process_variables(&self, engine: &mut impl ScriptEngine) -> Result<()> {
-        for (variable, value) in &self.source.script.request_variables {
+        let variables = self.source.script.request_variables.iter();
+        for (variable, value) in variables.chain(self.source.overrides) {
             let processed = engine.process(value.into())?;
             script_engine::inject_variable(engine, variable, processed)?;
         }
@@ -89,24 +158,342 @@ impl<'a> Executor<'a> {
             body,
             ..
         } = &self.source.script.request;
-        let headers = self.process_headers(engine, headers)?;
+        let mut headers = self.process_headers(engine, headers)?;
+        if matches!(method, parser::Method::Graphql(_))
+            && !headers
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        {
+            headers.push(("Content-Type".to_owned(), "application/json".to_owned()));
+        }
+
+        let mut options: RequestOptions = (&self.source.script.directives)
+            .try_into()
+            .with_context(|| format!("Failed processing directives of {}", self.request_name()))?;
+        if matches!(method, parser::Method::Grpc(_)) {
+            options.proto_files = self.proto_files(engine)?;
+        }
 
         Ok(Request {
+            options,
             method: method.into(),
             target: engine
-                .process(target.into())
+                .process_escaped(target.into(), Escaping::Url)
                 .with_context(|| format!("Failed processing: {}", target))?
                 .state
-                .value
-                .replace(|c: char| c.is_whitespace(), ""),
-            headers,
+                .value,
             body: match body {
-                None => None,
-                Some(body) => Some(engine.process(body.into())?.state.value),
+                // messages are processed one by one during the exchange
+                None | Some(parser::Body::Websocket { .. }) => None,
+                Some(body) => Some(self.process_body(engine, body, &headers)?),
             },
+            headers,
         })
     }
 
+    fn process_body(
+        &self,
+        engine: &mut impl ScriptEngine,
+        body: &parser::Body,
+        headers: &[(String, String)],
+    ) -> Result<Body> {
+        let (path, process_variables, selection) = match body {
+            parser::Body::Inline(value) => {
+                if let Some(boundary) = headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+                    .and_then(|(_, value)| multipart::boundary(value))
+                {
+                    return self.process_multipart(engine, value, boundary);
+                }
+
+                let body = engine
+                    .process_escaped(value.into(), body_escaping(headers))?
+                    .state
+                    .value;
+                // inline bodies are separated from the request by blank lines
+                return Ok(Body::Text(body.trim().to_owned()));
+            }
+            parser::Body::Graphql {
+                query, variables, ..
+            } => return self.process_graphql(engine, query, variables.as_ref()),
+            parser::Body::Websocket { .. } => {
+                return Err(anyhow!(
+                    "WebSocket messages can only be sent with a WEBSOCKET request"
+                ))
+            }
+            parser::Body::File {
+                path,
+                process_variables,
+                selection,
+            } => (path, *process_variables, selection),
+        };
+
+        let path = engine
+            .process(path.into())
+            .with_context(|| format!("Failed processing: {}", path))?
+            .state
+            .value;
+
+        self.read_body_file(
+            engine,
+            &selection.resolve_path(path.trim()),
+            process_variables,
+            body_escaping(headers),
+        )
+    }
+
+    /// `.proto` files listed in the `proto_files` variable, as an array or comma separated,
+    /// relative to the request file
+    fn proto_files(&self, engine: &mut impl ScriptEngine) -> Result<Vec<PathBuf>> {
+        let files = match engine.collection(PROTO_FILES_VARIABLE)? {
+            Some(files) => files,
+            None => match engine.variable(PROTO_FILES_VARIABLE)? {
+                Some(files) => files.split(',').map(str::to_owned).collect(),
+                None => return Ok(vec![]),
+            },
+        };
+
+        Ok(files
+            .iter()
+            .map(|it| it.trim())
+            .filter(|it| !it.is_empty())
+            .map(|it| self.source.script.selection.resolve_path(it))
+            .collect())
+    }
+
+    /// Wraps the query and its variables in the JSON envelope GraphQL servers expect over `POST`
+    fn process_graphql(
+        &self,
+        engine: &mut impl ScriptEngine,
+        query: &parser::Value,
+        variables: Option<&parser::Value>,
+    ) -> Result<Body> {
+        let query = engine.process(query.into())?.state.value;
+        let mut envelope = serde_json::Map::new();
+        envelope.insert("query".to_owned(), query.trim().into());
+
+        if let Some(variables) = variables {
+            let variables = engine
+                .process_escaped(variables.into(), Escaping::Json)?
+                .state
+                .value;
+            let variables: serde_json::Value =
+                serde_json::from_str(&variables).with_context(|| {
+                    format!(
+                        "GraphQL variables of {} are not valid JSON",
+                        self.request_name()
+                    )
+                })?;
+            envelope.insert("variables".to_owned(), variables);
+        }
+
+        Ok(Body::Text(serde_json::Value::Object(envelope).to_string()))
+    }
+
+    /// Splits the body into parts before substituting `{{ }}`, so that values containing
+    /// the boundary stay within their part
+    fn process_multipart<E: ScriptEngine>(
+        &self,
+        engine: &mut E,
+        body: &parser::Value,
+        boundary: &str,
+    ) -> Result<Body> {
+        let (inline_scripts, selection) = match (&body.state).into() {
+            script_engine::Unprocessed::WithInline {
+                inline_scripts,
+                selection,
+                ..
+            } => (inline_scripts, selection),
+            script_engine::Unprocessed::WithoutInline(_, selection) => (vec![], selection),
+        };
+        let mut inline_scripts = inline_scripts.into_iter().peekable();
+        let mut process = |engine: &mut E, text: String| -> Result<String> {
+            // `{{ }}` of the body are in order, so the ones of `text` are the next ones
+            let mut scripts = vec![];
+            let mut rest = text.as_str();
+            while let Some(script) = inline_scripts.peek() {
+                let Some(start) = rest.find(&script.placeholder) else {
+                    break;
+                };
+                rest = &rest[start + script.placeholder.len()..];
+                scripts.extend(inline_scripts.next());
+            }
+
+            let value = script_engine::Value {
+                state: script_engine::Unprocessed::WithInline {
+                    value: text,
+                    inline_scripts: scripts,
+                    selection: selection.clone(),
+                },
+            };
+            Ok(engine.process(value)?.state.value)
+        };
+
+        let parts = multipart::parse(&body.to_string(), boundary)
+            .with_context(|| format!("Failed parsing multipart body of {}", self.request_name()))?;
+
+        let mut processed = vec![];
+        for multipart::Part { headers, content } in parts {
+            let headers = headers
+                .into_iter()
+                .map(|(key, value)| Ok((key, process(engine, value)?)))
+                .collect::<Result<Vec<_>>>()?;
+            let body = match content {
+                multipart::Content::Text(text) => Body::Text(process(engine, text)?),
+                multipart::Content::File {
+                    path,
+                    process_variables,
+                } => {
+                    let path = process(engine, path)?;
+                    self.read_body_file(
+                        engine,
+                        &self.source.script.selection.resolve_path(path),
+                        process_variables,
+                        body_escaping(&headers),
+                    )?
+                }
+            };
+            processed.push(Part { headers, body });
+        }
+
+        Ok(Body::Multipart {
+            boundary: boundary.to_owned(),
+            parts: processed,
+        })
+    }
+
+    fn read_body_file(
+        &self,
+        engine: &mut impl ScriptEngine,
+        path: &Path,
+        process_variables: bool,
+        escaping: Escaping,
+    ) -> Result<Body> {
+        let content = fs::read(path)
+            .with_context(|| format!("Failed reading request body from `{}`", path.display()))?;
+
+        if !process_variables {
+            return Ok(content.into());
+        }
+
+        let content = String::from_utf8(content)
+            .with_context(|| format!("Request body `{}` is not valid UTF-8", path.display()))?;
+        let template = parser::parse_template(path.to_owned(), &content)
+            .with_context(|| format!("Failed parsing request body `{}`", path.display()))?;
+
+        Ok(Body::Text(
+            engine
+                .process_escaped((&template).into(), escaping)?
+                .state
+                .value,
+        ))
+    }
+
+    /// Sends the messages of a `WEBSOCKET` request, collecting the ones received from the server
+    async fn exchange<O: Output + ?Sized>(
+        &self,
+        client: &impl HttpClient,
+        engine: &mut impl ScriptEngine,
+        output: &mut O,
+        request: &Request,
+    ) -> Result<Response> {
+        let steps = match &self.source.script.request.body {
+            Some(parser::Body::Websocket { steps, .. }) => steps.as_slice(),
+            _ => &[],
+        };
+        let log = !self.source.script.directives.no_log;
+
+        let (mut response, mut connection) = client.connect(request).await?;
+        let mut messages = vec![];
+
+        for step in steps {
+            match step {
+                parser::WebsocketStep::Send(message) => {
+                    let message = engine
+                        .process(message.into())
+                        .with_context(|| format!("Failed processing: {}", message))?
+                        .state
+                        .value;
+                    let message = Message::Text(message.trim().to_owned());
+                    if log {
+                        output.message(&message, Direction::Sent)?;
+                    }
+                    connection.send(message).await?;
+                }
+                parser::WebsocketStep::WaitForServer => {
+                    let timeout = request.options.timeout.unwrap_or(WAIT_FOR_SERVER_TIMEOUT);
+                    let Some(message) = tokio::time::timeout(timeout, connection.receive())
+                        .await
+                        .with_context(|| {
+                            format!("No message from the server after {timeout:?}")
+                        })??
+                    else {
+                        break;
+                    };
+                    if log {
+                        output.message(&message, Direction::Received)?;
+                    }
+                    messages.push(message);
+                }
+            }
+        }
+
+        for message in connection.close().await? {
+            if log {
+                output.message(&message, Direction::Received)?;
+            }
+            messages.push(message);
+        }
+
+        response.messages = Some(messages);
+        Ok(response)
+    }
+
+    /// Reads messages of a streaming response as they arrive. With an `engine`, every message
+    /// is passed to the handler callbacks, and the stream is closed once they all unsubscribe
+    async fn receive<O: Output + ?Sized, E: ScriptEngine>(
+        &self,
+        mut stream: impl MessageStream,
+        response: &mut Response,
+        output: &mut O,
+        request: &Request,
+        mut engine: Option<&mut E>,
+    ) -> Result<()> {
+        let log = !self.source.script.directives.no_log;
+
+        let messages = response.messages.get_or_insert_with(Vec::new);
+        while request
+            .options
+            .max_events
+            .map_or(true, |max| messages.len() < max)
+        {
+            let Some(message) = stream.receive().await? else {
+                break;
+            };
+            if log {
+                output.message(&message, Direction::Received)?;
+            }
+            let subscribed = match engine.as_deref_mut() {
+                Some(engine) => {
+                    script_engine::receive_message(engine, &message).with_context(|| {
+                        format!(
+                            "Error handling response for request {}",
+                            self.request_name()
+                        )
+                    })?
+                }
+                None => true,
+            };
+            messages.push(message);
+            if !subscribed {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
     fn response_handler(
         &self,
         response: &Response,
@@ -134,39 +521,166 @@ impl<'a> Executor<'a> {
         engine.report().context("failed to get test report")
     }
 
-    pub(crate) async fn execute<O: Output + ?Sized>(
+    fn redirect_response(&self, engine: &mut impl ScriptEngine, response: &Response) -> Result<()> {
+        let Some(parser::Redirect {
+            path,
+            overwrite,
+            selection,
+        }) = &self.source.script.redirect
+        else {
+            return Ok(());
+        };
+
+        let path = engine
+            .process(path.into())
+            .with_context(|| format!("Failed processing: {}", path))?
+            .state
+            .value;
+        let mut path = selection.resolve_path(path.trim());
+        if !overwrite {
+            path = available_path(path);
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed creating directory `{}`", parent.display()))?;
+        }
+
+        // messages of duplex connections and streams are saved one per line
+        let content = match (&response.body, &response.messages) {
+            (None, Some(messages)) => messages
+                .iter()
+                .flat_map(|message| {
+                    let data = match message {
+                        Message::Text(text) => text.as_bytes(),
+                        Message::Binary(data) => data.as_slice(),
+                    };
+                    [data, b"\n"]
+                })
+                .flatten()
+                .copied()
+                .collect(),
+            (body, _) => Cow::Borrowed(body.as_deref().unwrap_or_default()),
+        };
+        fs::write(&path, content)
+            .with_context(|| format!("Failed writing response to `{}`", path.display()))?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn execute<O: Output + ?Sized, E: ScriptEngine>(
         &mut self,
         client: &impl HttpClient,
-        engine: &mut impl ScriptEngine,
+        engine: &mut E,
         output: &mut O,
     ) -> Result<(String, TestsReport)> {
         let name = self.request_name();
+        for (variable, value) in &self.iteration {
+            engine.define_variable(variable, value)?;
+        }
         self.process_variables(engine)?;
         self.pre_process_request(engine)?;
         let request = self.process_request(engine)?;
+        let log = !self.source.script.directives.no_log;
 
-        output.request(&request, &name)?;
+        if log {
+            output.request(&request, &name)?;
+        }
 
-        let response = client.execute(&request).await?;
-        let report = self.response_handler(&response, engine)?;
+        let (mut response, stream) = match request.method {
+            Method::Websocket => {
+                let exchange = self.exchange(client, engine, output, &request);
+                let response = match request.options.timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, exchange)
+                        .await
+                        .with_context(|| format!("{name} timed out after {timeout:?}"))??,
+                    None => exchange.await?,
+                };
+                (response, None)
+            }
+            _ => client.execute(&request).await?,
+        };
+        let subscribes = match (&stream, &self.source.script.handler) {
+            (Some(_), Some(parser::Handler { script, selection })) => engine
+                .subscribes_to_messages(
+                    &script_engine::Script {
+                        selection: selection.clone(),
+                        src: script.as_str(),
+                    },
+                    &response,
+                )
+                .context("failed to run the response handler")?,
+            _ => false,
+        };
+        let report = match stream {
+            Some(stream) if subscribes => {
+                // the handler is run first, so that its callbacks get messages as they arrive
+                response.messages = Some(vec![]);
+                self.response_handler(&response, engine)?;
+                self.receive(stream, &mut response, output, &request, Some(&mut *engine))
+                    .await?;
+                engine.report().context("failed to get test report")?
+            }
+            Some(stream) => {
+                self.receive(stream, &mut response, output, &request, None::<&mut E>)
+                    .await?;
+                self.response_handler(&response, engine)?
+            }
+            None => self.response_handler(&response, engine)?,
+        };
+        self.redirect_response(engine, &response)?;
 
-        output.response(&response, &report)?;
+        if log {
+            output.response(&response, &report)?;
+        }
 
         Ok((name, report))
     }
 }
 
+/// Finds a path that doesn't exist yet by appending `-1`, `-2`, ... to the file stem
+fn available_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|it| it.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|it| format!(".{}", it.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|index| path.with_file_name(format!("{stem}-{index}{extension}")))
+        .find(|candidate| !candidate.exists())
+        .expect("to find a free file name")
+}
+
+/// Escaping of `{{ }}` in a body with the given headers
+fn body_escaping(headers: &[(String, String)]) -> Escaping {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| Escaping::from_content_type(value))
+        .unwrap_or_default()
+}
+
 impl From<&parser::InlineScript> for script_engine::InlineScript {
     fn from(inline_script: &parser::InlineScript) -> Self {
         let parser::InlineScript {
             script,
             placeholder,
             selection,
+            raw,
         } = inline_script;
         script_engine::InlineScript {
             script: script.clone(),
             placeholder: placeholder.clone(),
             selection: selection.clone(),
+            raw: *raw,
         }
     }
 }
@@ -5,7 +5,7 @@ use postman::BodyClass;
 use serde_json::Value as DynValue;
 
 use crate::{
-    parser::{self},
+    parser::{self, multipart},
     source::SourceItem,
     EnvironmentProvider,
 };
@@ -120,7 +120,7 @@ impl CollectionExporter {
 
         item.event.extend(
             pre_request_helper
-                .variables(request_variables.iter())
+                .variables(request_variables.iter().chain(source.overrides))
                 .pre_request_handler(pre_request_handler)
                 .into_script(),
         );
@@ -162,17 +162,47 @@ impl CollectionExporter {
             .map(|it| postman::Header {
                 key: it.field_name.clone(),
                 value: helper.process(&it.field_value),
-                ..Default::default()
+                // postman generates its own boundary for `formdata` bodies
+                disabled: (it.field_name.eq_ignore_ascii_case("content-type")
+                    && multipart::boundary(it.field_value.state.value()).is_some())
+                .then_some(true),
             })
             .collect()
     }
 
     fn make_body(
-        body: &Option<parser::Value>,
+        body: &Option<parser::Body>,
         headers: &[parser::Header],
         helper: &mut PreRequestScriptHelper,
     ) -> Option<BodyClass> {
-        let body = body.as_ref()?;
+        let body = match body.as_ref()? {
+            parser::Body::Inline(value) => value,
+            parser::Body::File { path, .. } => {
+                return Some(postman::BodyClass {
+                    mode: Some(postman::Mode::File),
+                    file: Some(postman::File {
+                        content: None,
+                        src: Some(helper.process(path)),
+                    }),
+                    ..Default::default()
+                });
+            }
+            parser::Body::Websocket { .. } => return None,
+            parser::Body::Graphql {
+                query, variables, ..
+            } => {
+                return Some(postman::BodyClass {
+                    mode: Some(postman::Mode::Graphql),
+                    graphql: Some(postman::Graphql {
+                        query: helper.process(query).trim().to_owned(),
+                        variables: variables
+                            .as_ref()
+                            .map(|it| helper.process(it).trim().to_owned()),
+                    }),
+                    ..Default::default()
+                });
+            }
+        };
         let (mode, language) = Self::body_mode(headers);
 
         let processed_body = helper.process(body);
@@ -204,7 +234,18 @@ impl CollectionExporter {
                 }
                 class.urlencoded = Some(parameters);
             }
-            postman::Mode::File | postman::Mode::Formdata => { /* unimplemented!() */ }
+            postman::Mode::Formdata => {
+                let parts = headers
+                    .iter()
+                    .find(|h| h.field_name.eq_ignore_ascii_case("content-type"))
+                    .and_then(|h| multipart::boundary(h.field_value.state.value()))
+                    .and_then(|boundary| {
+                        multipart::parse(class.raw.as_ref().unwrap(), boundary).ok()
+                    });
+
+                class.formdata = parts.map(|parts| parts.iter().map(Into::into).collect());
+            }
+            postman::Mode::File | postman::Mode::Graphql => { /* unimplemented!() */ }
             postman::Mode::Raw => unreachable!(),
         }
 
@@ -230,7 +271,7 @@ impl CollectionExporter {
                 (postman::Mode::Raw, Some(postman::Language::Json))
             }
             "application/x-www-form-urlencoded" => (postman::Mode::Urlencoded, None),
-            "multipart/form-data" => (postman::Mode::Formdata, None),
+            x if x.starts_with("multipart/form-data") => (postman::Mode::Formdata, None),
             _ => (postman::Mode::Raw, None),
         }
     }
@@ -270,12 +311,21 @@ impl PreRequestScriptHelper {
 
         let mut value = value.clone();
 
-        if inline_scripts.iter().all(|it| !it.script.starts_with("$")) {
+        if inline_scripts.iter().all(|it| it.is_variable() && !it.raw) {
             return value.clone();
         }
 
         for script in inline_scripts {
+            if script.is_variable() {
+                // Postman substitutes values as they are anyway
+                if script.raw {
+                    value =
+                        value.replace(&script.placeholder, &format!("{{{{{}}}}}", script.script));
+                }
+                continue;
+            }
             if !script.script.starts_with("$") {
+                self.expression(&mut value, script);
                 continue;
             }
 
@@ -427,6 +477,22 @@ impl PreRequestScriptHelper {
         value
     }
 
+    /// Postman only substitutes variables, so expressions are evaluated by the pre-request script,
+    /// with variables in scope. If that fails, the placeholder is sent as it is
+    fn expression(&mut self, value: &mut String, script: &parser::InlineScript) {
+        let expression = script.script.trim();
+        let name = DynValue::from(expression);
+        // evaluated from a string, so that syntax errors are caught as well
+        let source = DynValue::from(format!("({expression}\n)"));
+
+        writeln!(
+            self.body,
+            "try {{\n    pm.variables.set({name}, (function () {{ with (pm.variables.toObject()) {{ return eval({source}); }} }})());\n}} catch (error) {{\n    console.warn('Failed evaluating', {name}, error);\n}}",
+        )
+        .unwrap();
+        *value = value.replace(&script.placeholder, &format!("{{{{{expression}}}}}"));
+    }
+
     fn into_script(self) -> Option<postman::Event> {
         if self.body.is_empty() {
             return None;
@@ -528,6 +594,29 @@ impl ResponseHandlerHelper {
     }
 }
 
+impl<'a> From<&'a multipart::Part> for postman::FormParameter {
+    fn from(part: &'a multipart::Part) -> Self {
+        let mut parameter = postman::FormParameter {
+            key: part.name().unwrap_or_default().to_owned(),
+            content_type: part.header("content-type").map(ToOwned::to_owned),
+            ..Default::default()
+        };
+
+        match &part.content {
+            multipart::Content::Text(text) => {
+                parameter.form_parameter_type = Some("text".to_owned());
+                parameter.value = Some(text.clone());
+            }
+            multipart::Content::File { path, .. } => {
+                parameter.form_parameter_type = Some("file".to_owned());
+                parameter.src = Some(vec![path.clone()]);
+            }
+        }
+
+        parameter
+    }
+}
+
 impl<'a> From<&'a parser::Method> for postman::Method {
     fn from(value: &'a parser::Method) -> Self {
         match value {
@@ -537,6 +626,14 @@ impl<'a> From<&'a parser::Method> for postman::Method {
             parser::Method::Put(_) => postman::Method::Put,
             parser::Method::Patch(_) => postman::Method::Patch,
             parser::Method::Options(_) => postman::Method::Options,
+            parser::Method::Head(_) => postman::Method::Head,
+            parser::Method::Trace(_) => postman::Method::Trace,
+            parser::Method::Connect(_) => postman::Method::Connect,
+            parser::Method::Graphql(_) => postman::Method::Post,
+            // collections have no WebSocket requests, export at least the handshake
+            parser::Method::Websocket(_) => postman::Method::Get,
+            parser::Method::Grpc(_) => postman::Method::Post,
+            parser::Method::Custom(method, _) => postman::Method::Custom(method.clone()),
         }
     }
 }
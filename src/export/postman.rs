@@ -191,7 +191,7 @@ pub(super) struct RequestClass {
     pub url: String,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, PartialEq, Default)]
+#[derive(Clone, Debug, Serialize, PartialEq, Default)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub(super) enum Method {
     #[default]
@@ -201,6 +201,12 @@ pub(super) enum Method {
     Put,
     Delete,
     Options,
+    Head,
+    Trace,
+    Connect,
+    /// Postman accepts any method name, e.g. `PROPFIND` or `PURGE`
+    #[serde(untagged)]
+    Custom(String),
 }
 
 #[derive(Clone, Debug, Serialize, PartialEq, Default)]
@@ -216,6 +222,9 @@ pub(super) struct BodyClass {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub formdata: Option<Vec<FormParameter>>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graphql: Option<Graphql>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub options: Option<Options>,
 
@@ -270,6 +279,15 @@ pub(super) struct File {
 }
 
 #[derive(Clone, Debug, Serialize, PartialEq)]
+pub(super) struct Graphql {
+    pub query: String,
+
+    /// JSON object of the query variables, as a string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variables: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Default)]
 #[serde(rename_all = "camelCase")]
 pub(super) struct FormParameter {
     /// Override Content-Type header of this form data entity.
@@ -328,13 +346,15 @@ pub(super) enum VariableType {
 /// Postman stores the type of data associated with this request in this field.
 #[derive(Clone, Copy, Debug, Serialize, PartialEq)]
 pub(super) enum Mode {
-    #[allow(unused)]
     #[serde(rename = "file")]
     File,
 
     #[serde(rename = "formdata")]
     Formdata,
 
+    #[serde(rename = "graphql")]
+    Graphql,
+
     #[serde(rename = "raw")]
     Raw,
 
@@ -0,0 +1,579 @@
+use std::path::PathBuf;
+
+use crate::{
+    parser::{self, Body, Comment, File, Handler, RequestScript, Run, Selection, WebsocketStep},
+    script_engine::escaping::Escaping,
+    Result,
+};
+
+/// Rewrites a `.http` file into the canonical layout:
+/// `###` between requests, a blank line between the parts of a request,
+/// `Name: value` headers, JSON bodies indented by two spaces
+/// and handler scripts indented by four spaces.
+/// Comments and directives are kept where they are
+pub fn format(filename: PathBuf, source: &str) -> Result<String> {
+    let file = parser::parse(filename, source)?;
+
+    Ok(Printer::new(source, &file.comments).file(&file))
+}
+
+/// Line of a request, or several of them, like a multiline target or a body
+struct Item {
+    /// Line of the item in the source, to put the comments preceding it before it
+    line: usize,
+    text: String,
+}
+
+enum Entry<'a> {
+    /// `import` or `run` line
+    Statement {
+        line: usize,
+        text: String,
+    },
+    Script(&'a RequestScript),
+}
+
+impl Entry<'_> {
+    fn line(&self) -> usize {
+        match self {
+            Entry::Statement { line, .. } => *line,
+            Entry::Script(script) => script.selection.start.line,
+        }
+    }
+}
+
+/// `run #name (@name=value, ...)`
+fn run_statement(run: &Run) -> String {
+    if run.overrides.is_empty() {
+        return format!("run {}", run.target);
+    }
+
+    let overrides = run
+        .overrides
+        .iter()
+        .map(|(name, value)| format!("@{name}={}", value.state.value()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("run {} ({overrides})", run.target)
+}
+
+struct Printer<'a> {
+    lines: Vec<&'a str>,
+    comments: &'a [Comment],
+    output: String,
+}
+
+impl<'a> Printer<'a> {
+    fn new(source: &'a str, comments: &'a [Comment]) -> Self {
+        Self {
+            lines: source.lines().collect(),
+            comments,
+            output: String::new(),
+        }
+    }
+
+    fn file(mut self, file: &File) -> String {
+        let mut entries = file
+            .imports
+            .iter()
+            .map(|import| Entry::Statement {
+                line: import.selection.start.line,
+                text: format!("import {}", import.path),
+            })
+            .chain(file.runs.iter().map(|run| Entry::Statement {
+                line: run.selection.start.line,
+                text: run_statement(run),
+            }))
+            .chain(file.request_scripts.iter().map(Entry::Script))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(Entry::line);
+
+        let mut after_script = false;
+        for (index, entry) in entries.iter().enumerate() {
+            let script = match entry {
+                Entry::Statement { line, text } => {
+                    // `import` and `run` lines are kept together, but a request before them
+                    // has to be ended with a separator
+                    if after_script {
+                        self.output.push('\n');
+                        self.comments_before(*line);
+                        self.line("###");
+                    } else {
+                        self.comments_before(*line);
+                    }
+                    self.line(text);
+                    after_script = false;
+                    continue;
+                }
+                Entry::Script(script) => script,
+            };
+            after_script = true;
+
+            if index > 0 {
+                self.output.push('\n');
+            }
+            self.comments_before(script.selection.start.line);
+
+            match self.separator(script) {
+                Some(separator) => self.line(&separator),
+                None if index > 0 => self.line("###"),
+                None => {}
+            }
+
+            for (index, block) in self.blocks(script).into_iter().enumerate() {
+                if index > 0 {
+                    self.output.push('\n');
+                }
+                for item in block {
+                    self.comments_before(item.line);
+                    self.line(&item.text);
+                }
+            }
+        }
+
+        if !self.comments.is_empty() && !self.output.is_empty() {
+            self.output.push('\n');
+        }
+        self.comments_before(usize::MAX);
+
+        self.output
+    }
+
+    fn line(&mut self, text: &str) {
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    fn comments_before(&mut self, line: usize) {
+        while let Some((comment, rest)) = self.comments.split_first() {
+            if comment.selection.start.line >= line {
+                break;
+            }
+            self.comments = rest;
+            self.line(&comment.text);
+        }
+    }
+
+    /// `### name`, if the request starts with a separator
+    fn separator(&self, script: &RequestScript) -> Option<String> {
+        let line = self.source_line(script.selection.start.line)?;
+        let name = line.trim().strip_prefix("###")?.trim();
+
+        match name {
+            "" => Some("###".to_owned()),
+            name => Some(format!("### {name}")),
+        }
+    }
+
+    /// Parts of the request, separated by a blank line
+    fn blocks(&self, script: &RequestScript) -> Vec<Vec<Item>> {
+        let request = &script.request;
+        let mut blocks = vec![];
+
+        blocks.push(
+            script
+                .request_variables
+                .iter()
+                .map(|(name, value)| Item {
+                    line: value.state.selection().start.line,
+                    text: format!("@{name} = {}", value.state.value().trim()),
+                })
+                .collect(),
+        );
+
+        if let Some(handler) = &script.pre_request_handler {
+            blocks.push(vec![self.handler("< {%", handler)]);
+        }
+
+        let target = request.target.state.selection();
+        let method = match request.method.selection() {
+            selection if *selection == Selection::none() => String::new(),
+            _ => format!("{} ", request.method),
+        };
+        let version = self
+            .source_line(target.end.line)
+            .map(|line| line.chars().skip(target.end.col - 1).collect::<String>())
+            .filter(|rest| rest.trim().starts_with("HTTP/"))
+            .map(|rest| format!(" {}", rest.trim()))
+            .unwrap_or_default();
+        let mut lines = vec![Item {
+            line: target.start.line,
+            text: format!(
+                "{method}{}{version}",
+                request.target.state.value().trim_end()
+            ),
+        }];
+        lines.extend(request.headers.iter().map(|header| Item {
+            line: header.selection.start.line,
+            text: format!(
+                "{}: {}",
+                header.field_name,
+                header.field_value.state.value().trim()
+            ),
+        }));
+        blocks.push(lines);
+
+        if let Some(body) = &request.body {
+            let content_type = request
+                .headers
+                .iter()
+                .find(|header| header.field_name.eq_ignore_ascii_case("content-type"))
+                .map(|header| header.field_value.state.value())
+                .unwrap_or_default();
+            let text = body_text(body, Escaping::from_content_type(content_type));
+            if !text.is_empty() {
+                blocks.push(vec![Item {
+                    line: body.selection().start.line,
+                    text,
+                }]);
+            }
+        }
+
+        if let Some(handler) = &script.handler {
+            blocks.push(vec![self.handler("> {%", handler)]);
+        }
+
+        if let Some(redirect) = &script.redirect {
+            let kind = if redirect.overwrite { ">>!" } else { ">>" };
+            blocks.push(vec![Item {
+                line: redirect.selection.start.line,
+                text: format!("{kind} {}", redirect.path.state.value().trim()),
+            }]);
+        }
+
+        blocks.retain(|block| !block.is_empty());
+        blocks
+    }
+
+    /// `> {% script %}` for scripts written on one line,
+    /// otherwise the script on its own lines, indented by four spaces
+    fn handler(&self, start: &str, handler: &Handler) -> Item {
+        let line = handler.selection.start.line;
+        let script = handler.script.trim_end();
+        if line == handler.selection.end.line {
+            return Item {
+                line,
+                text: format!("{start} {script} %}}"),
+            };
+        }
+
+        // the grammar skips the indentation of the first line
+        let first = script.lines().next().unwrap_or_default();
+        let indentation = (line..=handler.selection.end.line)
+            .filter_map(|line| self.source_line(line))
+            .find_map(|line| line.find(first).map(|offset| &line[..offset]))
+            .filter(|prefix| prefix.trim().is_empty())
+            .unwrap_or_default();
+
+        let lines = std::iter::once(format!("{indentation}{first}"))
+            .chain(script.lines().skip(1).map(str::to_owned))
+            .collect::<Vec<_>>();
+        let common = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or_default();
+
+        let mut text = format!("{start}\n");
+        for line in &lines {
+            if !line.trim().is_empty() {
+                text.push_str("    ");
+                text.push_str(&line[common..]);
+            }
+            text.push('\n');
+        }
+        text.push_str("%}");
+
+        Item { line, text }
+    }
+
+    fn source_line(&self, line: usize) -> Option<&'a str> {
+        self.lines.get(line.checked_sub(1)?).copied()
+    }
+}
+
+fn body_text(body: &Body, escaping: Escaping) -> String {
+    match body {
+        Body::Inline(value) => {
+            let text = value.state.value().trim();
+            match escaping {
+                Escaping::Json => pretty_json(text).unwrap_or_else(|| text.to_owned()),
+                _ => text.to_owned(),
+            }
+        }
+        Body::Graphql {
+            query, variables, ..
+        } => {
+            let query = query.state.value().trim();
+            match variables {
+                Some(variables) => {
+                    let variables = variables.state.value().trim();
+                    let variables = pretty_json(variables).unwrap_or_else(|| variables.to_owned());
+                    format!("{query}\n\n{variables}")
+                }
+                None => query.to_owned(),
+            }
+        }
+        Body::Websocket { steps, .. } => {
+            let mut messages = vec![];
+            for step in steps {
+                match step {
+                    WebsocketStep::Send(message) => {
+                        if !messages.is_empty() {
+                            messages.push("===".to_owned());
+                        }
+                        messages.push(message.state.value().trim().to_owned());
+                    }
+                    WebsocketStep::WaitForServer => messages.push("=== wait-for-server".to_owned()),
+                }
+            }
+            messages.join("\n")
+        }
+        Body::File { .. } => body.to_string().trim().to_owned(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Punctuation(char),
+    /// String, number, `true`, `false` or `null`
+    Literal(&'a str),
+    /// `{{ }}` outside of a string
+    InlineScript(&'a str),
+}
+
+/// Splits JSON with `{{ }}` into tokens, keeping strings and numbers as they are written
+fn tokens(json: &str) -> Option<Vec<Token<'_>>> {
+    let mut tokens = vec![];
+    let mut rest = json.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '{' if rest.starts_with("{{") => {
+                let len = rest.find("}}")? + 2;
+                tokens.push(Token::InlineScript(&rest[..len]));
+                len
+            }
+            '{' | '}' | '[' | ']' | ',' | ':' => {
+                tokens.push(Token::Punctuation(c));
+                1
+            }
+            '"' => {
+                let mut escaped = false;
+                let (end, _) = rest.char_indices().skip(1).find(|&(_, c)| {
+                    let end = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    end
+                })?;
+                tokens.push(Token::Literal(&rest[..=end]));
+                end + 1
+            }
+            _ => {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || "{}[],:\"".contains(c))
+                    .unwrap_or(rest.len());
+                tokens.push(Token::Literal(&rest[..len]));
+                len
+            }
+        };
+        rest = rest[len..].trim_start();
+    }
+
+    Some(tokens)
+}
+
+/// Indents JSON by two spaces, or `None` if it is not valid JSON
+fn pretty_json(json: &str) -> Option<String> {
+    let tokens = tokens(json)?;
+
+    let substituted = tokens
+        .iter()
+        .map(|token| match token {
+            Token::Punctuation(c) => c.to_string(),
+            Token::Literal(literal) => literal.to_string(),
+            Token::InlineScript(_) => "null".to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    serde_json::from_str::<serde_json::Value>(&substituted).ok()?;
+
+    let mut output = String::new();
+    let mut indentation = 0;
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Punctuation(open @ ('{' | '[')) => {
+                output.push(open);
+                if let Some(&Token::Punctuation(close @ ('}' | ']'))) = tokens.peek() {
+                    output.push(close);
+                    tokens.next();
+                } else {
+                    indentation += 1;
+                    new_line(&mut output, indentation);
+                }
+            }
+            Token::Punctuation(close @ ('}' | ']')) => {
+                indentation -= 1;
+                new_line(&mut output, indentation);
+                output.push(close);
+            }
+            Token::Punctuation(',') => {
+                output.push(',');
+                new_line(&mut output, indentation);
+            }
+            Token::Punctuation(':') => output.push_str(": "),
+            Token::Punctuation(c) => output.push(c),
+            Token::Literal(text) | Token::InlineScript(text) => output.push_str(text),
+        }
+    }
+
+    Some(output)
+}
+
+fn new_line(output: &mut String, indentation: usize) {
+    output.push('\n');
+    output.push_str(&"  ".repeat(indentation));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn request() {
+        let source = "\
+# login first
+@user  =   admin
+# @no-log
+POST   http://{{host}}/login HTTP/1.1
+Content-Type:application/json
+Accept:   */*
+
+{\"user\": \"{{user}}\", \"id\": {{id}}, \"tags\": [], \"n\": 1.50}
+> {% client.global.set(\"token\", response.body.token); %}
+###   next
+# @name second
+GET http://{{host}}/get
+> {%
+  client.test(\"ok\", () => {
+      client.assert(response.status == 200);
+  });
+%}
+>> ./out.json
+# the end
+";
+
+        let formatted = format(PathBuf::from("test.http"), source).unwrap();
+        assert_eq!(
+            formatted,
+            "\
+# login first
+@user = admin
+
+# @no-log
+POST http://{{host}}/login HTTP/1.1
+Content-Type: application/json
+Accept: */*
+
+{
+  \"user\": \"{{user}}\",
+  \"id\": {{id}},
+  \"tags\": [],
+  \"n\": 1.50
+}
+
+> {% client.global.set(\"token\", response.body.token); %}
+
+### next
+# @name second
+GET http://{{host}}/get
+
+> {%
+    client.test(\"ok\", () => {
+        client.assert(response.status == 200);
+    });
+%}
+
+>> ./out.json
+
+# the end
+"
+        );
+        assert_eq!(
+            format(PathBuf::from("test.http"), &formatted).unwrap(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn import_and_run() {
+        let source = "\
+import   ./auth.http
+run #Login   (@user = admin,@id={{id}})
+GET http://localhost/profile
+###
+run ./cleanup.http
+";
+
+        let formatted = format(PathBuf::from("test.http"), source).unwrap();
+        assert_eq!(
+            formatted,
+            "\
+import ./auth.http
+run #Login (@user=admin, @id={{id}})
+
+###
+GET http://localhost/profile
+
+###
+run ./cleanup.http
+"
+        );
+        assert_eq!(
+            format(PathBuf::from("test.http"), &formatted).unwrap(),
+            formatted
+        );
+    }
+
+    #[test]
+    fn invalid_json_is_kept() {
+        let source = "POST http://localhost\nContent-Type: application/json\n\n{\"a\": 1,,}\n";
+
+        let formatted = format(PathBuf::from("test.http"), source).unwrap();
+        assert_eq!(formatted, source);
+    }
+
+    #[test]
+    fn idempotent() {
+        for entry in fs::read_dir("tests/requests").unwrap() {
+            let path = entry.unwrap().path();
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "http")
+            {
+                continue;
+            }
+
+            let source = fs::read_to_string(&path).unwrap();
+            let formatted = format(path.clone(), &source).unwrap();
+            let requests = parser::parse(path.clone(), &source).unwrap();
+            let reformatted = parser::parse(path.clone(), &formatted).unwrap();
+
+            assert_eq!(
+                requests.request_scripts.len(),
+                reformatted.request_scripts.len(),
+                "{}",
+                path.display()
+            );
+            assert_eq!(
+                format(path.clone(), &formatted).unwrap(),
+                formatted,
+                "{}",
+                path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use std::{collections::HashSet, io, path::PathBuf, str::FromStr, sync::Arc};
+
+use color_eyre::eyre::{anyhow, Context};
+use futures_util::StreamExt;
+use prost::Message as _;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor};
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{self, ring, WebPkiSupportedAlgorithms},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        DigitallySignedStruct, SignatureScheme,
+    },
+    TlsConnector,
+};
+use tonic::{
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    codegen::http::{uri::PathAndQuery, StatusCode, Uri},
+    metadata::{MetadataKey, MetadataMap, MetadataValue},
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Code, Status,
+};
+use tonic_reflection::pb::{
+    server_reflection_client::ServerReflectionClient, server_reflection_request::MessageRequest,
+    server_reflection_response::MessageResponse, ServerReflectionRequest,
+};
+
+use crate::{
+    http::{Body, Message, Request, Response, Version},
+    ClientConfig, Result,
+};
+
+/// Calls `package.Service/Method` of `host/package.Service/Method`, translating the JSON body
+/// to protobuf and the response back to JSON.
+///
+/// Services are described by `options.proto_files`, or by the server reflection if there are none.
+pub async fn execute(request: &Request, config: &ClientConfig) -> Result<Response> {
+    let (endpoint, service, method) = parse_target(&request.target)?;
+    let channel = connect(&endpoint, request, config).await?;
+
+    let pool = if request.options.proto_files.is_empty() {
+        reflect(channel.clone(), service)
+            .await
+            .with_context(|| format!("Failed resolving `{service}` with server reflection"))?
+    } else {
+        compile(&request.options.proto_files)?
+    };
+    let method = find_method(&pool, service, method)?;
+
+    let body = match &request.body {
+        Some(Body::Text(text)) if !text.trim().is_empty() => text.as_str(),
+        None | Some(Body::Text(_)) => "{}",
+        Some(_) => return Err(anyhow!("gRPC requests expect a JSON body")),
+    };
+    let mut deserializer = serde_json::Deserializer::from_str(body);
+    let message = DynamicMessage::deserialize(method.input(), &mut deserializer)
+        .with_context(|| format!("Invalid `{}` message", method.input().full_name()))?;
+    deserializer.end()?;
+
+    let mut grpc_request = tonic::Request::new(message);
+    for (key, value) in &request.headers {
+        // set by tonic itself
+        if key.eq_ignore_ascii_case("content-type") {
+            continue;
+        }
+        grpc_request.metadata_mut().append(
+            MetadataKey::from_bytes(key.to_ascii_lowercase().as_bytes())
+                .with_context(|| format!("Invalid metadata key: {key}"))?,
+            MetadataValue::from_str(value)
+                .with_context(|| format!("Invalid value of metadata {key}"))?,
+        );
+    }
+    if let Some(timeout) = request.options.timeout {
+        grpc_request.set_timeout(timeout);
+    }
+
+    let path = PathAndQuery::from_str(&format!("/{}/{}", service, method.name()))?;
+    let codec = DynamicCodec(method.output());
+    let mut client = tonic::client::Grpc::new(channel);
+    client.ready().await?;
+
+    if method.is_client_streaming() {
+        return Err(anyhow!(
+            "`{}` is a client streaming method, which is not supported",
+            method.full_name()
+        ));
+    }
+
+    if method.is_server_streaming() {
+        let result = client.server_streaming(grpc_request, path, codec).await;
+        let (metadata, mut stream) = match result {
+            Ok(response) => {
+                let (metadata, stream, _) = response.into_parts();
+                (metadata, stream)
+            }
+            Err(status) => return Ok(status_response(&status)),
+        };
+
+        let mut messages = vec![];
+        let mut values = vec![];
+        let mut status = Status::new(Code::Ok, "");
+        while let Some(message) = stream.next().await {
+            match message {
+                Ok(message) => {
+                    let value = serde_json::to_value(&message)?;
+                    messages.push(Message::Text(value.to_string()));
+                    values.push(value);
+                }
+                Err(error) => {
+                    status = error;
+                    break;
+                }
+            }
+        }
+        let trailers = stream.trailers().await.ok().flatten();
+
+        let mut response = response(&metadata, &status, Some(values.into()));
+        if let Some(trailers) = trailers {
+            response.headers.extend(metadata_headers(&trailers));
+        }
+        response.messages = Some(messages);
+        return Ok(response);
+    }
+
+    match client.unary(grpc_request, path, codec).await {
+        Ok(response) => {
+            let body = serde_json::to_value(response.get_ref())?;
+            Ok(self::response(
+                response.metadata(),
+                &Status::new(Code::Ok, ""),
+                Some(body),
+            ))
+        }
+        Err(status) => Ok(status_response(&status)),
+    }
+}
+
+/// Splits `host/package.Service/Method` into the endpoint, the service and the method
+fn parse_target(target: &str) -> Result<(String, &str, &str)> {
+    let (scheme, rest) = match target.split_once("://") {
+        Some(("grpcs" | "https", rest)) => ("https", rest),
+        Some(("grpc" | "http", rest)) => ("http", rest),
+        Some((scheme, _)) => return Err(anyhow!("Unsupported gRPC scheme `{scheme}`")),
+        None => ("http", target),
+    };
+
+    let invalid =
+        || anyhow!("Expected gRPC target as `host/package.Service/Method`, got `{target}`");
+    let (authority, path) = rest.split_once('/').ok_or_else(invalid)?;
+    let (service, method) = path
+        .trim_end_matches('/')
+        .rsplit_once('/')
+        .ok_or_else(invalid)?;
+
+    Ok((format!("{scheme}://{authority}"), service, method))
+}
+
+fn find_method(pool: &DescriptorPool, service: &str, method: &str) -> Result<MethodDescriptor> {
+    pool.get_service_by_name(service)
+        .ok_or_else(|| anyhow!("Unknown gRPC service `{service}`"))?
+        .methods()
+        .find(|it| it.name() == method)
+        .ok_or_else(|| anyhow!("Unknown method `{method}` of gRPC service `{service}`"))
+}
+
+/// Compiles `.proto` files, with their directories as include paths
+fn compile(files: &[PathBuf]) -> Result<DescriptorPool> {
+    let includes = files
+        .iter()
+        .map(|file| {
+            file.parent()
+                .filter(|it| !it.as_os_str().is_empty())
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|| PathBuf::from("."))
+        })
+        .collect::<Vec<_>>();
+
+    let descriptors = protox::compile(files, includes).context("Failed compiling proto files")?;
+
+    Ok(DescriptorPool::from_file_descriptor_set(descriptors)?)
+}
+
+/// Fetches the file declaring `service` and all of its dependencies from the server reflection
+async fn reflect(channel: Channel, service: &str) -> Result<DescriptorPool> {
+    let mut client = ServerReflectionClient::new(channel);
+    let mut files: Vec<FileDescriptorProto> = vec![];
+    let mut known = HashSet::new();
+    let mut pending = vec![MessageRequest::FileContainingSymbol(service.to_owned())];
+
+    while let Some(message_request) = pending.pop() {
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(message_request),
+        };
+        let mut responses = client
+            .server_reflection_info(futures_util::stream::iter([request]))
+            .await?
+            .into_inner();
+
+        while let Some(response) = responses.next().await {
+            let protos = match response?.message_response {
+                Some(MessageResponse::FileDescriptorResponse(response)) => {
+                    response.file_descriptor_proto
+                }
+                Some(MessageResponse::ErrorResponse(error)) => {
+                    return Err(anyhow!("{}", error.error_message))
+                }
+                _ => continue,
+            };
+
+            for proto in protos {
+                let proto = FileDescriptorProto::decode(proto.as_slice())?;
+                if !known.insert(proto.name().to_owned()) {
+                    continue;
+                }
+                pending.extend(
+                    proto
+                        .dependency
+                        .iter()
+                        .filter(|it| !known.contains(*it))
+                        .map(|it| MessageRequest::FileByFilename(it.clone())),
+                );
+                files.push(proto);
+            }
+        }
+    }
+
+    Ok(DescriptorPool::from_file_descriptor_set(
+        FileDescriptorSet { file: files },
+    )?)
+}
+
+async fn connect(endpoint: &str, request: &Request, config: &ClientConfig) -> Result<Channel> {
+    let uri =
+        Uri::from_str(endpoint).with_context(|| format!("Invalid gRPC endpoint: {endpoint}"))?;
+    let https = uri.scheme_str() == Some("https");
+
+    let mut endpoint = if https && !config.ssl_check {
+        // tonic always verifies certificates of `https` endpoints, so the connector of a plain
+        // one sets up TLS instead, while requests keep the `https` origin
+        let host = uri.host().unwrap_or_default();
+        let port = uri.port_u16().unwrap_or(443);
+        Endpoint::from_shared(format!("http://{host}:{port}"))?.origin(uri.clone())
+    } else if https {
+        Endpoint::from(uri.clone()).tls_config(ClientTlsConfig::new())?
+    } else {
+        Endpoint::from(uri.clone())
+    };
+    if let Some(timeout) = request.options.connection_timeout {
+        endpoint = endpoint.connect_timeout(timeout);
+    }
+
+    let channel = if https && !config.ssl_check {
+        let connector = insecure_tls_connector();
+        endpoint
+            .connect_with_connector(tower::service_fn(move |uri| {
+                connect_tls(connector.clone(), uri)
+            }))
+            .await
+    } else {
+        endpoint.connect().await
+    };
+    channel.with_context(|| format!("Failed connecting to {uri}"))
+}
+
+/// TLS connector accepting any certificate of the server
+fn insecure_tls_connector() -> TlsConnector {
+    let mut config = rustls::ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(NoCertificateVerification(
+            ring::default_provider().signature_verification_algorithms,
+        )))
+        .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    TlsConnector::from(Arc::new(config))
+}
+
+async fn connect_tls(connector: TlsConnector, uri: Uri) -> io::Result<TlsStream<TcpStream>> {
+    let host = uri.host().unwrap_or_default().to_owned();
+    let stream = TcpStream::connect((host.as_str(), uri.port_u16().unwrap_or(443))).await?;
+    let name = ServerName::try_from(host)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    connector.connect(name, stream).await
+}
+
+/// Accepts any certificate, while still checking the handshake signatures made with it
+#[derive(Debug)]
+struct NoCertificateVerification(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+fn response(metadata: &MetadataMap, status: &Status, body: Option<serde_json::Value>) -> Response {
+    let mut headers = metadata_headers(metadata);
+    headers.push(("grpc-status".to_owned(), (status.code() as i32).to_string()));
+    if !status.message().is_empty() {
+        headers.push(("grpc-message".to_owned(), status.message().to_owned()));
+    }
+    // the body is decoded to JSON, unlike what the server has sent
+    headers.push(("content-type".to_owned(), "application/json".to_owned()));
+
+    let status_code = http_status(status.code());
+    Response {
+        version: Version::Http2,
+        status_code: status_code.as_u16(),
+        status: status_code.to_string(),
+        headers,
+        body: body.map(|it| serde_json::to_vec_pretty(&it).expect("JSON to be serializable")),
+        messages: None,
+    }
+}
+
+/// HTTP status corresponding to the status of the call, the way gRPC gateways map them
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::Cancelled => StatusCode::from_u16(499).expect("499 to be a valid status"),
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn status_response(status: &Status) -> Response {
+    response(status.metadata(), status, None)
+}
+
+fn metadata_headers(metadata: &MetadataMap) -> Vec<(String, String)> {
+    metadata
+        .clone()
+        .into_headers()
+        .iter()
+        // status is reported from `Status`, as it might be either in headers or trailers
+        .filter(|(key, _)| {
+            !matches!(
+                key.as_str(),
+                "content-type" | "grpc-status" | "grpc-message"
+            )
+        })
+        .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_owned())))
+        .collect()
+}
+
+/// Encodes any message, and decodes messages of the given type
+#[derive(Clone)]
+struct DynamicCodec(MessageDescriptor);
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicCodec;
+    type Decoder = DynamicCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        self.clone()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        self.clone()
+    }
+}
+
+impl Encoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(
+        &mut self,
+        item: Self::Item,
+        dst: &mut EncodeBuf<'_>,
+    ) -> std::result::Result<(), Self::Error> {
+        item.encode(dst)
+            .map_err(|error| Status::internal(error.to_string()))
+    }
+}
+
+impl Decoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(
+        &mut self,
+        src: &mut DecodeBuf<'_>,
+    ) -> std::result::Result<Option<Self::Item>, Self::Error> {
+        DynamicMessage::decode(self.0.clone(), src)
+            .map(Some)
+            .map_err(|error| Status::internal(error.to_string()))
+    }
+}
@@ -1,10 +1,16 @@
-use std::future::Future;
+use std::{borrow::Cow, future::Future, path::PathBuf, time::Duration};
+
+use color_eyre::eyre::{anyhow, Context};
+use encoding_rs::Encoding;
 
 use crate::{parser, ClientConfig, Result};
 
+pub mod grpc;
 pub mod reqwest;
+pub mod stream;
+pub mod websocket;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Method {
     Get,
     Post,
@@ -12,6 +18,14 @@ pub enum Method {
     Put,
     Patch,
     Options,
+    Head,
+    Trace,
+    Connect,
+    /// `GET` upgraded to a WebSocket connection
+    Websocket,
+    /// gRPC call, the target is `host/package.Service/Method`
+    Grpc,
+    Custom(String),
 }
 
 #[derive(Clone, Debug)]
@@ -19,7 +33,117 @@ pub struct Request {
     pub method: Method,
     pub target: String,
     pub headers: Vec<(String, String)>,
-    pub body: Option<String>,
+    pub body: Option<Body>,
+    pub options: RequestOptions,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RequestOptions {
+    /// Do not follow redirects, return the redirect response as is
+    pub no_redirect: bool,
+    /// Timeout for the whole request, from connecting until the response body is read
+    pub timeout: Option<Duration>,
+    pub connection_timeout: Option<Duration>,
+    /// Neither send cookies from the cookie jar nor save the received ones
+    pub no_cookie_jar: bool,
+    /// `.proto` files describing gRPC services, server reflection is used if there are none
+    pub proto_files: Vec<PathBuf>,
+    /// Stop reading a streaming response after this many messages
+    pub max_events: Option<usize>,
+}
+
+impl TryFrom<&parser::Directives> for RequestOptions {
+    type Error = crate::Error;
+
+    fn try_from(directives: &parser::Directives) -> Result<Self> {
+        let duration = |name: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(parse_duration)
+                .transpose()
+                .with_context(|| format!("Invalid `@{name}` directive"))
+        };
+
+        Ok(RequestOptions {
+            no_redirect: directives.no_redirect,
+            timeout: duration("timeout", &directives.timeout)?,
+            connection_timeout: duration("connection-timeout", &directives.connection_timeout)?,
+            no_cookie_jar: directives.no_cookie_jar,
+            proto_files: vec![],
+            max_events: directives
+                .max_events
+                .as_deref()
+                .map(|it| it.trim().parse())
+                .transpose()
+                .context("Invalid `@max-events` directive, expected a number")?,
+        })
+    }
+}
+
+/// Parses durations like `600` (seconds), `500 ms`, `30 s` or `2 m`
+fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| anyhow!("Expected a duration, got `{value}`"))?;
+
+    match unit.trim() {
+        "ms" => Ok(Duration::from_millis(amount)),
+        "" | "s" => Ok(Duration::from_secs(amount)),
+        "m" => Ok(Duration::from_secs(amount * 60)),
+        unit => Err(anyhow!(
+            "Unknown duration unit `{unit}`, expected `ms`, `s` or `m`"
+        )),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Body {
+    Text(String),
+    Binary(Vec<u8>),
+    Multipart { boundary: String, parts: Vec<Part> },
+}
+
+#[derive(Clone, Debug)]
+pub struct Part {
+    pub headers: Vec<(String, String)>,
+    pub body: Body,
+}
+
+impl Body {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Body::Text(text) => text.as_bytes().to_vec(),
+            Body::Binary(data) => data.clone(),
+            Body::Multipart { boundary, parts } => {
+                let mut output = vec![];
+                for Part { headers, body } in parts {
+                    output.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+                    for (key, value) in headers {
+                        output.extend_from_slice(format!("{key}: {value}\r\n").as_bytes());
+                    }
+                    output.extend_from_slice(b"\r\n");
+                    output.extend_from_slice(&body.to_bytes());
+                    output.extend_from_slice(b"\r\n");
+                }
+                output.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+                output
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(data: Vec<u8>) -> Self {
+        match String::from_utf8(data) {
+            Ok(text) => Body::Text(text),
+            Err(error) => Body::Binary(error.into_bytes()),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -28,7 +152,91 @@ pub struct Response {
     pub status_code: u16,
     pub status: String,
     pub headers: Vec<(String, String)>,
-    pub body: Option<String>,
+    pub body: Option<Vec<u8>>,
+    /// Messages received over a duplex connection or of a streaming response,
+    /// `None` for plain HTTP responses
+    pub messages: Option<Vec<Message>>,
+}
+
+/// Message of a duplex connection, or an event of a streaming response
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Which side of a duplex connection has sent a message
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Body decoded with the charset from `Content-Type`, or as UTF-8 if there is none.
+    /// Returns `None` for binary bodies.
+    pub fn text(&self) -> Option<Cow<'_, str>> {
+        let body = self.body.as_deref()?;
+        let content_type = self.header("content-type").unwrap_or_default();
+
+        if let Some(encoding) = charset(content_type).and_then(Encoding::for_label) {
+            let (text, _, _) = encoding.decode(body);
+            return Some(text);
+        }
+
+        if is_binary_mime(content_type) {
+            return None;
+        }
+
+        std::str::from_utf8(body).ok().map(Cow::Borrowed)
+    }
+}
+
+fn charset(content_type: &str) -> Option<&[u8]> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|it| it.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').as_bytes())
+}
+
+fn is_binary_mime(content_type: &str) -> bool {
+    const BINARY: &[&str] = &[
+        "application/octet-stream",
+        "application/pdf",
+        "application/zip",
+        "application/gzip",
+        "application/x-gzip",
+        "application/x-tar",
+        "application/protobuf",
+        "application/x-protobuf",
+        "application/grpc",
+        "application/wasm",
+    ];
+
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if mime.ends_with("+xml") || mime.ends_with("+json") {
+        return false;
+    }
+
+    ["image/", "audio/", "video/", "font/"]
+        .iter()
+        .any(|prefix| mime.starts_with(prefix))
+        || BINARY.contains(&mime.as_str())
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -41,11 +249,37 @@ pub enum Version {
 }
 
 pub trait HttpClient {
-    fn create(config: ClientConfig) -> Self
+    fn create(config: ClientConfig) -> Result<Self>
     where
         Self: Sized;
 
-    fn execute(&self, request: &Request) -> impl Future<Output = Result<Response>>;
+    /// Sends the request. Streaming responses, such as `text/event-stream`, are returned without
+    /// a body, along with the stream of their messages
+    fn execute(
+        &self,
+        request: &Request,
+    ) -> impl Future<Output = Result<(Response, Option<impl MessageStream>)>>;
+
+    /// Opens a duplex connection, returning the handshake response along with it
+    fn connect(
+        &self,
+        request: &Request,
+    ) -> impl Future<Output = Result<(Response, impl DuplexConnection)>>;
+}
+
+pub trait MessageStream {
+    /// Waits for the next message, `None` once the response has ended
+    fn receive(&mut self) -> impl Future<Output = Result<Option<Message>>>;
+}
+
+pub trait DuplexConnection {
+    fn send(&mut self, message: Message) -> impl Future<Output = Result<()>>;
+
+    /// Waits for the next message, `None` once the connection is closed
+    fn receive(&mut self) -> impl Future<Output = Result<Option<Message>>>;
+
+    /// Closes the connection, returning messages the server sent in the meantime
+    fn close(self) -> impl Future<Output = Result<Vec<Message>>>;
 }
 
 impl From<&parser::Method> for Method {
@@ -57,6 +291,13 @@ impl From<&parser::Method> for Method {
             parser::Method::Put(_) => Method::Put,
             parser::Method::Patch(_) => Method::Patch,
             parser::Method::Options(_) => Method::Options,
+            parser::Method::Head(_) => Method::Head,
+            parser::Method::Trace(_) => Method::Trace,
+            parser::Method::Connect(_) => Method::Connect,
+            parser::Method::Graphql(_) => Method::Post,
+            parser::Method::Websocket(_) => Method::Websocket,
+            parser::Method::Grpc(_) => Method::Grpc,
+            parser::Method::Custom(method, _) => Method::Custom(method.clone()),
         }
     }
 }
@@ -1,58 +1,202 @@
 use std::{
     borrow::Cow,
+    collections::HashMap,
     convert::{TryFrom, TryInto},
+    fs,
+    io::{self, BufReader},
+    path::Path,
     str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
-use color_eyre::eyre::Context;
+use color_eyre::eyre::{anyhow, Context};
+use cookie_store::CookieStore;
 use http::Uri;
-use reqwest::{header::HeaderMap, Client, RequestBuilder, Url};
+use reqwest::{header::HeaderMap, redirect, Client, ClientBuilder, RequestBuilder, Url};
+use reqwest_cookie_store::CookieStoreMutex;
 
 use crate::{
-    http::{ClientConfig, HttpClient, Method, Request, Response, Version},
+    http::{
+        grpc,
+        stream::{ResponseStream, StreamKind},
+        websocket, Body, ClientConfig, DuplexConnection, HttpClient, MessageStream, Method,
+        Request, RequestOptions, Response, Version,
+    },
     Result,
 };
 
 pub struct ReqwestHttpClient {
+    config: ClientConfig,
+    cookies: Arc<CookieStoreMutex>,
     client: Client,
+    /// Clients for requests with client-level options, by the options they were built with
+    clients: Mutex<HashMap<ClientOptions, Client>>,
+}
+
+/// Options of a request that `reqwest` only supports for a whole client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ClientOptions {
+    no_cookie_jar: bool,
+    no_redirect: bool,
+    connection_timeout: Option<Duration>,
+    /// Connection upgrades, like the WebSocket handshake, are only possible over HTTP/1.1
+    http1_only: bool,
 }
 
 impl Default for ReqwestHttpClient {
     fn default() -> Self {
-        Self::create(ClientConfig::default())
+        Self::create(ClientConfig::default()).expect("default client to be valid")
+    }
+}
+
+impl ReqwestHttpClient {
+    /// Writes persistent cookies to the cookie jar file, if there is one.
+    /// The file is not created while there are no such cookies.
+    pub fn save_cookies(&self) -> Result<()> {
+        let Some(path) = &self.config.cookie_jar else {
+            return Ok(());
+        };
+
+        let cookies = self
+            .cookies
+            .lock()
+            .map_err(|_| anyhow!("Cookie jar is poisoned"))?;
+        if !cookies.iter_unexpired().any(|it| it.is_persistent()) && !path.exists() {
+            return Ok(());
+        }
+
+        let mut writer = fs::File::create(path)?;
+        cookies
+            .save_json(&mut writer)
+            .map_err(|error| anyhow!("{error}"))?;
+
+        Ok(())
+    }
+
+    /// Clients are configured once, so requests with client-level options get a dedicated one,
+    /// which is reused by the following requests with the same options
+    fn client(&self, options: &RequestOptions, http1_only: bool) -> Result<Client> {
+        let options = ClientOptions {
+            no_cookie_jar: options.no_cookie_jar,
+            no_redirect: options.no_redirect,
+            connection_timeout: options.connection_timeout,
+            http1_only,
+        };
+        if !options.no_cookie_jar
+            && !options.no_redirect
+            && options.connection_timeout.is_none()
+            && !options.http1_only
+        {
+            return Ok(self.client.clone());
+        }
+
+        let mut clients = self
+            .clients
+            .lock()
+            .map_err(|_| anyhow!("Client cache is poisoned"))?;
+        if let Some(client) = clients.get(&options) {
+            return Ok(client.clone());
+        }
+
+        let mut builder = client_builder(&self.config);
+        if !options.no_cookie_jar {
+            builder = builder.cookie_provider(self.cookies.clone());
+        }
+        if options.no_redirect {
+            builder = builder.redirect(redirect::Policy::none());
+        }
+        if let Some(timeout) = options.connection_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if options.http1_only {
+            builder = builder.http1_only();
+        }
+        let client = builder.build()?;
+        clients.insert(options, client.clone());
+
+        Ok(client)
     }
 }
 
 impl HttpClient for ReqwestHttpClient {
-    fn create(config: ClientConfig) -> ReqwestHttpClient
+    fn create(config: ClientConfig) -> Result<ReqwestHttpClient>
     where
         Self: Sized,
     {
-        let client = Client::builder()
-            .danger_accept_invalid_certs(config.ssl_check)
-            .build()
-            .unwrap();
+        let cookies = Arc::new(CookieStoreMutex::new(load_cookies(
+            config.cookie_jar.as_deref(),
+        )?));
 
-        ReqwestHttpClient { client }
+        let client = client_builder(&config)
+            .cookie_provider(cookies.clone())
+            .build()?;
+
+        Ok(ReqwestHttpClient {
+            config,
+            cookies,
+            client,
+            clients: Mutex::default(),
+        })
     }
 
-    async fn execute(&self, request: &Request) -> Result<Response> {
+    async fn execute(&self, request: &Request) -> Result<(Response, Option<impl MessageStream>)> {
         let Request {
             method,
             target,
             headers,
             body,
+            options,
         } = request;
-        let mut request_builder = self
-            .client
-            .request(method.into(), get_request_target(target)?);
+        if *method == Method::Grpc {
+            return Ok((grpc::execute(request, &self.config).await?, None));
+        }
+
+        let client = self.client(options, false)?;
+        let mut request_builder = client.request(method.try_into()?, get_request_target(target)?);
+        if let Some(timeout) = options.timeout {
+            request_builder = request_builder.timeout(timeout);
+        }
         request_builder = set_headers(headers, request_builder);
         if let Some(body) = body {
             request_builder = set_body(body, request_builder);
         }
         let response = request_builder.send().await?;
 
-        map_reqwest_response(response).await
+        let kind = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|it| it.to_str().ok())
+            .and_then(StreamKind::from_content_type);
+        match kind {
+            Some(kind) => {
+                let mut mapped = map_reqwest_head(&response)?;
+                mapped.messages = Some(vec![]);
+                Ok((mapped, Some(ResponseStream::new(response, kind))))
+            }
+            None => Ok((map_reqwest_response(response).await?, None)),
+        }
+    }
+
+    async fn connect(&self, request: &Request) -> Result<(Response, impl DuplexConnection)> {
+        websocket::connect(&self.client(&request.options, true)?, request).await
+    }
+}
+
+fn client_builder(config: &ClientConfig) -> ClientBuilder {
+    Client::builder().danger_accept_invalid_certs(!config.ssl_check)
+}
+
+fn load_cookies(path: Option<&Path>) -> Result<CookieStore> {
+    let Some(path) = path else {
+        return Ok(CookieStore::default());
+    };
+
+    match fs::File::open(path) {
+        Ok(file) => CookieStore::load_json_all(BufReader::new(file))
+            .map_err(|error| anyhow!("Failed loading cookie jar `{}`: {error}", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(CookieStore::default()),
+        Err(e) => Err(e).with_context(|| format!("Failed opening `{}`", path.display())),
     }
 }
 
@@ -87,32 +231,53 @@ fn set_headers(
     request_builder
 }
 
-impl From<&Method> for reqwest::Method {
-    fn from(method: &Method) -> Self {
-        match method {
+impl TryFrom<&Method> for reqwest::Method {
+    type Error = crate::Error;
+
+    fn try_from(method: &Method) -> Result<Self> {
+        Ok(match method {
             Method::Get => reqwest::Method::GET,
             Method::Post => reqwest::Method::POST,
             Method::Delete => reqwest::Method::DELETE,
             Method::Put => reqwest::Method::PUT,
             Method::Patch => reqwest::Method::PATCH,
             Method::Options => reqwest::Method::OPTIONS,
-        }
+            Method::Head => reqwest::Method::HEAD,
+            Method::Trace => reqwest::Method::TRACE,
+            Method::Connect => reqwest::Method::CONNECT,
+            Method::Websocket => {
+                return Err(anyhow!(
+                    "WebSocket requests can only be sent with `connect`"
+                ))
+            }
+            Method::Grpc => return Err(anyhow!("gRPC requests are not sent over plain HTTP")),
+            Method::Custom(method) => reqwest::Method::from_bytes(method.as_bytes())
+                .with_context(|| format!("Invalid method: {method}"))?,
+        })
     }
 }
 
 struct Headers(Vec<(String, String)>);
 
 async fn map_reqwest_response(response: reqwest::Response) -> Result<Response> {
+    let mut mapped = map_reqwest_head(&response)?;
+    mapped.body = match response.bytes().await? {
+        body if !body.is_empty() => Some(body.to_vec()),
+        _ => None,
+    };
+    Ok(mapped)
+}
+
+/// Status line and headers of the response, without reading its body
+fn map_reqwest_head(response: &reqwest::Response) -> Result<Response> {
     let Headers(headers) = response.headers().try_into()?;
     Ok(Response {
         version: response.version().into(),
         status_code: response.status().as_u16(),
         status: response.status().to_string(),
         headers,
-        body: match response.text().await? {
-            body if !body.is_empty() => Some(body),
-            _ => None,
-        },
+        body: None,
+        messages: None,
     })
 }
 
@@ -141,8 +306,7 @@ impl From<reqwest::Version> for Version {
     }
 }
 
-fn set_body(body: &str, mut request_builder: RequestBuilder) -> RequestBuilder {
-    let body = body.trim();
-    request_builder = request_builder.body::<reqwest::Body>(body.to_string().into());
+fn set_body(body: &Body, mut request_builder: RequestBuilder) -> RequestBuilder {
+    request_builder = request_builder.body(body.to_bytes());
     request_builder
 }
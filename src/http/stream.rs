@@ -0,0 +1,205 @@
+use std::{collections::VecDeque, mem};
+
+use crate::{
+    http::{Message, MessageStream},
+    Result,
+};
+
+/// Format of a response that is read event by event instead of buffered as a whole
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StreamKind {
+    /// `text/event-stream`, every event with `data` is a message
+    ServerSentEvents,
+    /// Newline delimited JSON, every non-empty line is a message
+    Lines,
+}
+
+impl StreamKind {
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        match mime.as_str() {
+            "text/event-stream" => Some(StreamKind::ServerSentEvents),
+            "application/x-ndjson"
+            | "application/ndjson"
+            | "application/jsonl"
+            | "application/x-jsonlines"
+            | "application/stream+json" => Some(StreamKind::Lines),
+            _ => None,
+        }
+    }
+}
+
+/// Splits chunks of a streaming body into messages
+#[derive(Debug)]
+pub struct Decoder {
+    kind: StreamKind,
+    buffer: Vec<u8>,
+    /// `data` lines of the server-sent event being read
+    data: Vec<String>,
+}
+
+impl Decoder {
+    pub fn new(kind: StreamKind) -> Self {
+        Self {
+            kind,
+            buffer: vec![],
+            data: vec![],
+        }
+    }
+
+    /// Messages completed by `chunk`, the rest is kept until the next one
+    pub fn decode(&mut self, chunk: &[u8]) -> Vec<Message> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut messages = vec![];
+        while let Some(end) = self.buffer.iter().position(|it| *it == b'\n') {
+            let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            messages.extend(self.line(line.trim_end_matches(['\n', '\r'])));
+        }
+
+        messages
+    }
+
+    /// Messages left once the body has ended, even if they are not terminated
+    pub fn finish(&mut self) -> Vec<Message> {
+        let rest = mem::take(&mut self.buffer);
+        let rest = String::from_utf8_lossy(&rest);
+
+        let mut messages = vec![];
+        if !rest.trim().is_empty() {
+            messages.extend(self.line(rest.trim_end_matches(['\n', '\r'])));
+        }
+        messages.extend(self.line(""));
+        messages
+    }
+
+    fn line(&mut self, line: &str) -> Option<Message> {
+        match self.kind {
+            StreamKind::Lines if line.trim().is_empty() => None,
+            StreamKind::Lines => Some(Message::Text(line.to_owned())),
+            // blank line dispatches the event
+            StreamKind::ServerSentEvents if line.is_empty() => {
+                if self.data.is_empty() {
+                    return None;
+                }
+                Some(Message::Text(mem::take(&mut self.data).join("\n")))
+            }
+            StreamKind::ServerSentEvents => {
+                let (field, value) = line.split_once(':').unwrap_or((line, ""));
+                // other fields, and comments with an empty field name, are skipped
+                if field == "data" {
+                    self.data
+                        .push(value.strip_prefix(' ').unwrap_or(value).to_owned());
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Messages of a streaming `reqwest` response, read as they arrive
+pub struct ResponseStream {
+    response: reqwest::Response,
+    decoder: Decoder,
+    pending: VecDeque<Message>,
+    finished: bool,
+}
+
+impl ResponseStream {
+    pub fn new(response: reqwest::Response, kind: StreamKind) -> Self {
+        Self {
+            response,
+            decoder: Decoder::new(kind),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+impl MessageStream for ResponseStream {
+    async fn receive(&mut self) -> Result<Option<Message>> {
+        loop {
+            if let Some(message) = self.pending.pop_front() {
+                return Ok(Some(message));
+            }
+            if self.finished {
+                return Ok(None);
+            }
+
+            match self.response.chunk().await {
+                Ok(Some(chunk)) => self.pending.extend(self.decoder.decode(&chunk)),
+                Ok(None) => {
+                    self.finished = true;
+                    self.pending.extend(self.decoder.finish());
+                }
+                // `@timeout` ends the stream, keeping the messages received before it
+                Err(error) if error.is_timeout() => {
+                    self.finished = true;
+                    self.pending.extend(self.decoder.finish());
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(messages: Vec<Message>) -> Vec<String> {
+        messages
+            .into_iter()
+            .map(|it| match it {
+                Message::Text(text) => text,
+                Message::Binary(_) => panic!("Expected text message"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kind_from_content_type() {
+        assert_eq!(
+            StreamKind::from_content_type("text/event-stream; charset=utf-8"),
+            Some(StreamKind::ServerSentEvents)
+        );
+        assert_eq!(
+            StreamKind::from_content_type("application/x-ndjson"),
+            Some(StreamKind::Lines)
+        );
+        assert_eq!(StreamKind::from_content_type("application/json"), None);
+    }
+
+    #[test]
+    fn server_sent_events() {
+        let mut decoder = Decoder::new(StreamKind::ServerSentEvents);
+
+        let messages = decoder.decode(b": keep-alive\n\nevent: greeting\ndata: hel");
+        assert!(messages.is_empty());
+
+        let messages = decoder.decode(b"lo\r\nid: 1\n\ndata: first\ndata:second\n\ndata: last");
+        assert_eq!(text(messages), ["hello", "first\nsecond"]);
+
+        assert_eq!(text(decoder.finish()), ["last"]);
+    }
+
+    #[test]
+    fn lines() {
+        let mut decoder = Decoder::new(StreamKind::Lines);
+
+        let messages = decoder.decode(b"{\"id\": 1}\n\n{\"id\"");
+        assert_eq!(text(messages), ["{\"id\": 1}"]);
+
+        let messages = decoder.decode(b": 2}\n{\"id\": 3}");
+        assert_eq!(text(messages), ["{\"id\": 2}"]);
+
+        assert_eq!(text(decoder.finish()), ["{\"id\": 3}"]);
+    }
+}
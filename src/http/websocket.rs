@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{anyhow, Context};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{header, Client, Upgraded};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        handshake::{client::generate_key, derive_accept_key},
+        protocol::Role,
+    },
+    WebSocketStream,
+};
+
+use crate::{
+    http::{DuplexConnection, Message, Request, Response, Version},
+    Result,
+};
+
+pub struct WebsocketConnection {
+    stream: WebSocketStream<Upgraded>,
+}
+
+/// Performs the handshake with `client`, so the connection shares its TLS settings and cookies
+pub async fn connect(
+    client: &Client,
+    request: &Request,
+) -> Result<(Response, WebsocketConnection)> {
+    let target = match request.target.split_once("://") {
+        Some(("ws", rest)) => format!("http://{rest}"),
+        Some(("wss", rest)) => format!("https://{rest}"),
+        Some(_) => request.target.clone(),
+        None => format!("http://{}", request.target),
+    };
+
+    let key = generate_key();
+    let mut handshake = client
+        .get(&target)
+        .header(header::CONNECTION, "Upgrade")
+        .header(header::UPGRADE, "websocket")
+        .header(header::SEC_WEBSOCKET_VERSION, "13")
+        .header(header::SEC_WEBSOCKET_KEY, &key);
+    for (key, value) in &request.headers {
+        handshake = handshake.header(key, value);
+    }
+
+    let response = handshake
+        .send()
+        .await
+        .with_context(|| format!("Failed connecting to {}", request.target))?;
+    if response.status() != reqwest::StatusCode::SWITCHING_PROTOCOLS {
+        return Err(anyhow!(
+            "Server refused the WebSocket connection with status {}",
+            response.status()
+        ));
+    }
+    let accept = response.headers().get(header::SEC_WEBSOCKET_ACCEPT);
+    if accept.map(|it| it.as_bytes()) != Some(derive_accept_key(key.as_bytes()).as_bytes()) {
+        return Err(anyhow!(
+            "Server sent an invalid Sec-WebSocket-Accept header"
+        ));
+    }
+
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(key, value)| Ok((key.to_string(), value.to_str()?.to_owned())))
+        .collect::<Result<_>>()?;
+    let mapped = Response {
+        version: Version::Http11,
+        status_code: response.status().as_u16(),
+        status: response.status().to_string(),
+        headers,
+        body: None,
+        messages: None,
+    };
+
+    let upgraded = response
+        .upgrade()
+        .await
+        .context("Failed upgrading to a WebSocket connection")?;
+    let stream = WebSocketStream::from_raw_socket(upgraded, Role::Client, None).await;
+
+    Ok((mapped, WebsocketConnection { stream }))
+}
+
+/// How long to wait for the server to acknowledge closing the connection
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl DuplexConnection for WebsocketConnection {
+    async fn send(&mut self, message: Message) -> Result<()> {
+        let message = match message {
+            Message::Text(text) => tungstenite::Message::Text(text),
+            Message::Binary(data) => tungstenite::Message::Binary(data),
+        };
+        self.stream.send(message).await?;
+
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> Result<Option<Message>> {
+        while let Some(message) = self.stream.next().await {
+            match message? {
+                tungstenite::Message::Text(text) => return Ok(Some(Message::Text(text))),
+                tungstenite::Message::Binary(data) => return Ok(Some(Message::Binary(data))),
+                tungstenite::Message::Close(_) => return Ok(None),
+                // pings are answered by tungstenite itself
+                tungstenite::Message::Ping(_)
+                | tungstenite::Message::Pong(_)
+                | tungstenite::Message::Frame(_) => {}
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn close(mut self) -> Result<Vec<Message>> {
+        match self.stream.close(None).await {
+            Ok(())
+            | Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        let mut messages = vec![];
+        let draining = async {
+            while let Some(message) = self.receive().await? {
+                messages.push(message);
+            }
+            Result::Ok(())
+        };
+
+        // a server that never acknowledges closing keeps the messages received so far
+        if let Ok(Err(error)) = tokio::time::timeout(CLOSE_TIMEOUT, draining).await {
+            if !matches!(
+                error.downcast_ref(),
+                Some(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed)
+            ) {
+                return Err(error);
+            }
+        }
+
+        Ok(messages)
+    }
+}
@@ -1,4 +1,7 @@
-use std::borrow::BorrowMut;
+// `Option::is_none_or`, which the lint suggests, needs a newer Rust than the crate supports
+#![allow(clippy::unnecessary_map_or)]
+
+use std::{borrow::BorrowMut, path::PathBuf};
 
 use color_eyre::{eyre::Context, Report};
 
@@ -16,7 +19,9 @@ use crate::{
 mod environment;
 mod executor;
 pub mod export;
+pub mod format;
 mod http;
+pub mod lsp;
 pub mod output;
 pub(crate) mod parser;
 mod script_engine;
@@ -27,17 +32,26 @@ pub type Result<T> = color_eyre::Result<T>;
 
 pub struct ClientConfig {
     pub ssl_check: bool,
+    /// File to load cookies from and to save them to after execution.
+    /// Without it cookies are only kept in memory.
+    pub cookie_jar: Option<PathBuf>,
 }
 
 impl Default for ClientConfig {
     fn default() -> Self {
-        Self { ssl_check: true }
+        Self {
+            ssl_check: true,
+            cookie_jar: None,
+        }
     }
 }
 
 impl ClientConfig {
     pub fn new(ssl_check: bool) -> Self {
-        Self { ssl_check }
+        Self {
+            ssl_check,
+            ..Default::default()
+        }
     }
 }
 
@@ -55,7 +69,7 @@ where
 {
     pub fn new(environment: &'a mut E, output: &'a mut O, config: ClientConfig) -> Result<Self> {
         let engine = create_script_engine(environment)?;
-        let client = ReqwestHttpClient::create(config);
+        let client = ReqwestHttpClient::create(config)?;
 
         Ok(Runtime {
             output,
@@ -65,6 +79,13 @@ where
         })
     }
 
+    /// Fail before sending a request with variables that are not defined,
+    /// instead of sending their `{{name}}` as it is.
+    pub fn with_strict_variables(mut self, strict_variables: bool) -> Self {
+        self.engine = self.engine.with_strict_variables(strict_variables);
+        self
+    }
+
     pub async fn execute(&mut self, mut source_provider: impl SourceProvider) -> Result<()> {
         let engine = &mut self.engine;
         let output = self.output.borrow_mut();
@@ -73,17 +94,17 @@ where
         let mut files_requests_tests = vec![];
 
         for source in source_provider.requests() {
-            let (_, report) = Executor::new(source)
-                .execute(client, engine, output)
-                .await?;
+            for mut executor in Executor::iterations(source, engine)? {
+                let (_, report) = executor.execute(client, engine, output).await?;
 
-            files_requests_tests.push((
-                source.source_name().to_owned(),
-                source.request_name(),
-                report,
-            ));
+                files_requests_tests.push((
+                    source.source_name().to_owned(),
+                    executor.name(),
+                    report,
+                ));
 
-            engine.reset()?;
+                engine.reset()?;
+            }
         }
 
         output.tests(files_requests_tests)?;
@@ -96,6 +117,10 @@ where
             .save(&snapshot)
             .with_context(|| "Error writing snapshot")?;
 
+        self.client
+            .save_cookies()
+            .with_context(|| "Error writing cookie jar")?;
+
         Ok(())
     }
 }
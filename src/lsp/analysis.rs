@@ -0,0 +1,443 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use lsp_types::{
+    CodeLens, Command, CompletionItem, CompletionItemKind, CompletionTextEdit, Diagnostic,
+    DiagnosticSeverity, Hover, HoverContents, Location, MarkupContent, MarkupKind, Position, Range,
+    TextEdit, Url,
+};
+use serde_json::json;
+
+use crate::{
+    lsp::RUN_COMMAND,
+    parser::{self, diagnostic::Severity, Selection},
+};
+
+/// Dynamic variables, with the description shown on hover
+const DYNAMIC_VARIABLES: &[(&str, &str)] = &[
+    ("$random.uuid", "Random UUID v4"),
+    (
+        "$random.integer",
+        "Random integer between `0` and `1000`, or between `from` and `to` with `$random.integer(from, to)`",
+    ),
+    (
+        "$random.float",
+        "Random floating point number between `0.0` and `1000.0`, or between `from` and `to` with `$random.float(from, to)`",
+    ),
+    (
+        "$random.alphabetic",
+        "`$random.alphabetic(length)`, random sequence of uppercase and lowercase letters",
+    ),
+    (
+        "$random.alphanumeric",
+        "`$random.alphanumeric(length)`, random sequence of letters, digits and underscores",
+    ),
+    (
+        "$random.hexadecimal",
+        "`$random.hexadecimal(length)`, random hexadecimal string",
+    ),
+    ("$random.email", "Random email address"),
+    ("$timestamp", "Current UNIX timestamp, in seconds"),
+    ("$isoTimestamp", "Current local time in the RFC 3339 format"),
+    (
+        "$processEnv",
+        "`$processEnv NAME`, variable of the process environment",
+    ),
+    (
+        "$dotenv",
+        "`$dotenv NAME`, variable of the `.env` file next to the request file",
+    ),
+    ("$env", "`$env.NAME`, variable of the process environment"),
+];
+
+pub fn diagnostics(path: &Path, text: &str) -> Vec<Diagnostic> {
+    let diagnostics = match parser::parse(path.to_owned(), text) {
+        Ok(file) => file.warnings,
+        Err(error) => match error.downcast::<parser::Error>() {
+            Ok(error) => error.diagnostics,
+            Err(error) => {
+                return vec![diagnostic(
+                    Range::default(),
+                    DiagnosticSeverity::ERROR,
+                    format!("{error:#}"),
+                )]
+            }
+        },
+    };
+
+    diagnostics
+        .iter()
+        .map(|it| {
+            let message = match &it.hint {
+                Some(hint) => format!("{}\n{hint}", it.message),
+                None => it.message.clone(),
+            };
+            let severity = match it.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+            };
+            diagnostic(range(text, &it.selection), severity, message)
+        })
+        .collect()
+}
+
+fn diagnostic(range: Range, severity: DiagnosticSeverity, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some("dothttp".to_owned()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// Variables of the environment, the request file and dynamic ones, inside of a `{{ }}`
+pub fn completion(text: &str, position: Position, environment: &[String]) -> Vec<CompletionItem> {
+    let Some(script) = InlineScriptAt::find(text, position) else {
+        return vec![];
+    };
+    let prefix = script.prefix.trim_start();
+    let range = Range::new(
+        Position::new(
+            position.line,
+            position.character - prefix.encode_utf16().count() as u32,
+        ),
+        position,
+    );
+
+    let declared = request_variables(text)
+        .into_iter()
+        .map(|(name, _)| (name, "request variable".to_owned()));
+    let environment = environment
+        .iter()
+        .map(|name| (name.clone(), "environment variable".to_owned()));
+    let dynamic = DYNAMIC_VARIABLES
+        .iter()
+        .map(|(name, description)| (name.to_string(), description.to_string()));
+
+    let mut items: Vec<CompletionItem> = vec![];
+    for (name, detail) in declared.chain(environment).chain(dynamic) {
+        if !name.starts_with(prefix) || items.iter().any(|item| item.label == name) {
+            continue;
+        }
+
+        items.push(CompletionItem {
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(detail),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, name.clone()))),
+            label: name,
+            ..Default::default()
+        });
+    }
+
+    items
+}
+
+/// Description of the dynamic variable under the cursor
+pub fn hover(text: &str, position: Position) -> Option<Hover> {
+    let script = InlineScriptAt::find(text, position)?;
+    let script = script.script.trim();
+
+    let (name, description) = DYNAMIC_VARIABLES
+        .iter()
+        .filter(|(name, _)| {
+            script.starts_with(name)
+                && !script[name.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+        })
+        .max_by_key(|(name, _)| name.len())?;
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: format!("`{name}`\n\n{description}"),
+        }),
+        range: None,
+    })
+}
+
+/// `@name = ...` declaration of the variable under the cursor, or its key in the environment files
+pub fn definition(
+    uri: &Url,
+    text: &str,
+    position: Position,
+    environment_files: &[PathBuf],
+) -> Option<Location> {
+    let script = InlineScriptAt::find(text, position)?;
+    let name = script.script.trim();
+    if !parser::is_variable_name(name) {
+        return None;
+    }
+
+    let declarations = request_variables(text)
+        .into_iter()
+        .filter(|(declared, _)| declared == name)
+        .map(|(_, position)| position)
+        .collect::<Vec<_>>();
+    // the closest declaration above the cursor is the one that is used
+    let declaration = declarations
+        .iter()
+        .rev()
+        .find(|it| it.line <= position.line)
+        .or(declarations.first());
+    if let Some(&declaration) = declaration {
+        return Some(Location::new(
+            uri.clone(),
+            Range::new(declaration, declaration),
+        ));
+    }
+
+    let key = name.split('.').next().unwrap_or(name);
+    environment_files.iter().find_map(|file| {
+        let contents = fs::read_to_string(file).ok()?;
+        let position = json_key(&contents, key)?;
+        let uri = Url::from_file_path(fs::canonicalize(file).ok()?).ok()?;
+        Some(Location::new(uri, Range::new(position, position)))
+    })
+}
+
+/// "Run" above every request
+pub fn code_lenses(uri: &Url, path: &Path, text: &str) -> Vec<CodeLens> {
+    let Ok(file) = parser::parse(path.to_owned(), text) else {
+        return vec![];
+    };
+
+    file.request_scripts
+        .iter()
+        .enumerate()
+        .map(|(index, script)| {
+            let request = &script.request;
+            let line = match request.method.selection() {
+                selection if *selection == Selection::none() => {
+                    request.target.state.selection().start.line
+                }
+                selection => selection.start.line,
+            };
+            let title = match &script.name {
+                Some(name) => format!("▶ Run `{name}`"),
+                None => format!("▶ Run #{}", index + 1),
+            };
+            let start = Position::new(line.saturating_sub(1) as u32, 0);
+
+            CodeLens {
+                range: Range::new(start, start),
+                command: Some(Command {
+                    title,
+                    command: RUN_COMMAND.to_owned(),
+                    arguments: Some(vec![json!(uri), json!(index + 1)]),
+                }),
+                data: None,
+            }
+        })
+        .collect()
+}
+
+/// Parser positions count characters, while LSP ones count UTF-16 code units by default
+fn range(text: &str, selection: &Selection) -> Range {
+    let position = |position: &parser::Position| {
+        let line = position.line.saturating_sub(1);
+        let character = text
+            .lines()
+            .nth(line)
+            .unwrap_or_default()
+            .chars()
+            .take(position.col.saturating_sub(1))
+            .map(char::len_utf16)
+            .sum::<usize>();
+        Position::new(line as u32, character as u32)
+    };
+
+    let start = position(&selection.start);
+    let mut end = position(&selection.end);
+    if end == start {
+        end.character += 1;
+    }
+
+    Range::new(start, end)
+}
+
+/// `{{ }}` the cursor is in
+struct InlineScriptAt<'a> {
+    /// Text from the `{{` to the cursor
+    prefix: &'a str,
+    /// Text between the `{{` and the `}}`
+    script: &'a str,
+}
+
+impl<'a> InlineScriptAt<'a> {
+    fn find(text: &'a str, position: Position) -> Option<Self> {
+        let line = text.lines().nth(position.line as usize)?;
+        let cursor = byte_offset(line, position.character);
+
+        let start = line[..cursor].rfind("{{")? + 2;
+        if line[start..cursor].contains("}}") {
+            return None;
+        }
+        let end = line[cursor..]
+            .find("}}")
+            .map(|offset| cursor + offset)
+            .unwrap_or(line.len());
+
+        Some(Self {
+            prefix: &line[start..cursor],
+            script: &line[start..end],
+        })
+    }
+}
+
+/// Names of `@name = value` declarations, with the position of their `@`.
+/// The text is scanned rather than parsed, so that they are found in a file with errors as well
+fn request_variables(text: &str) -> Vec<(String, Position)> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let (name, _) = text.trim_start().strip_prefix('@')?.split_once('=')?;
+            let name = name.trim();
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return None;
+            }
+
+            let column = text.len() - text.trim_start().len();
+            Some((
+                name.to_owned(),
+                Position::new(line as u32, character(text, column)),
+            ))
+        })
+        .collect()
+}
+
+/// Position of `"key":` in a JSON file
+fn json_key(contents: &str, key: &str) -> Option<Position> {
+    let quoted = format!("\"{key}\"");
+    contents.lines().enumerate().find_map(|(line, text)| {
+        let column = text.find(&quoted)?;
+        text[column + quoted.len()..]
+            .trim_start()
+            .starts_with(':')
+            .then(|| Position::new(line as u32, character(text, column)))
+    })
+}
+
+/// Column in UTF-16 code units of the byte `offset` of the line
+fn character(line: &str, offset: usize) -> u32 {
+    line[..offset].encode_utf16().count() as u32
+}
+
+/// Byte offset of the column in UTF-16 code units, the end of the line past it
+fn byte_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= character as usize {
+            return offset;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+@user = admin
+
+GET http://{{host}}/users/{{user}}?id={{$random.integer(1, 10)}}
+
+###
+GET http://{{host}}/{{ us
+";
+
+    #[test]
+    fn diagnostics_of_file() {
+        let diagnostics = diagnostics(Path::new("test.http"), FILE);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(5, 20));
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert!(diagnostics[0].message.starts_with("unterminated `{{`"));
+    }
+
+    #[test]
+    fn completion_of_variables() {
+        let environment = ["host".to_owned(), "username".to_owned()];
+
+        let labels = completion(FILE, Position::new(5, 25), &environment)
+            .into_iter()
+            .map(|item| item.label)
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["user", "username"]);
+
+        let items = completion(FILE, Position::new(2, 41), &environment);
+        assert!(items.iter().any(|item| item.label == "$random.integer"));
+
+        assert!(completion(FILE, Position::new(2, 5), &environment).is_empty());
+    }
+
+    #[test]
+    fn positions_in_utf16() {
+        // the emoji is one character, but two UTF-16 code units
+        let text = "GET http://{{host}}/😀/{{ us\n";
+
+        let diagnostics = diagnostics(Path::new("test.http"), text);
+        assert_eq!(diagnostics[0].range.start, Position::new(0, 23));
+
+        let items = completion(text, Position::new(0, 28), &["user".to_owned()]);
+        let Some(CompletionTextEdit::Edit(edit)) = &items[0].text_edit else {
+            panic!("Expected a text edit")
+        };
+        assert_eq!(edit.range.start, Position::new(0, 26));
+    }
+
+    #[test]
+    fn hover_of_dynamic_variable() {
+        let Some(Hover {
+            contents: HoverContents::Markup(markup),
+            ..
+        }) = hover(FILE, Position::new(2, 45))
+        else {
+            panic!("Expected hover")
+        };
+        assert!(markup.value.starts_with("`$random.integer`"));
+
+        assert!(hover(FILE, Position::new(2, 15)).is_none());
+    }
+
+    #[test]
+    fn definition_of_variable() {
+        let dir = tempfile::tempdir().unwrap();
+        let environment_file = dir.path().join("http-client.env.json");
+        fs::write(
+            &environment_file,
+            "{\n  \"dev\": {\n    \"host\": \"localhost\"\n  }\n}\n",
+        )
+        .unwrap();
+        let uri = Url::parse("file:///test.http").unwrap();
+
+        let location = definition(&uri, FILE, Position::new(2, 30), &[]).unwrap();
+        assert_eq!(location.uri, uri);
+        assert_eq!(location.range.start, Position::new(0, 0));
+
+        let location = definition(&uri, FILE, Position::new(2, 14), &[environment_file]).unwrap();
+        assert!(location.uri.path().ends_with("http-client.env.json"));
+        assert_eq!(location.range.start, Position::new(2, 4));
+    }
+
+    #[test]
+    fn code_lens_for_each_request() {
+        let uri = Url::parse("file:///test.http").unwrap();
+        let text = "GET http://localhost\n\n### login\nPOST http://localhost/login\n";
+
+        let lenses = code_lenses(&uri, Path::new("test.http"), text);
+
+        let lenses = lenses
+            .iter()
+            .map(|lens| {
+                let command = lens.command.as_ref().unwrap();
+                (lens.range.start.line, command.title.as_str())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lenses, [(0, "▶ Run #1"), (3, "▶ Run `login`")]);
+    }
+}
@@ -0,0 +1,387 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use color_eyre::eyre::{anyhow, Context};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, LogMessage,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{CodeLensRequest, Completion, ExecuteCommand, GotoDefinition, HoverRequest},
+    CodeLensOptions, CodeLensParams, CompletionOptions, CompletionParams,
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    ExecuteCommandOptions, ExecuteCommandParams, GotoDefinitionParams, HoverParams,
+    HoverProviderCapability, LogMessageParams, MessageType, OneOf, PublishDiagnosticsParams,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    output::{parse_format, print::FormattedOutput},
+    source::FileSourceProvider,
+    ClientConfig, EnvironmentProvider, Result, Runtime,
+};
+
+mod analysis;
+
+/// Command of the code lenses, runs the request with the given number of the file
+pub const RUN_COMMAND: &str = "dothttp.run";
+
+const REQUEST_FORMAT: &str = "%N\n%R\n\n";
+const RESPONSE_FORMAT: &str = "%R\n%H\n%B\n\n%T\n";
+
+/// Runs the language server on stdin and stdout, until the client shuts it down.
+/// `environment` is opened every time it is needed, so that changes to its files are picked up
+pub fn serve<E, F>(environment: F, environment_files: Vec<PathBuf>) -> Result<()>
+where
+    E: EnvironmentProvider,
+    F: Fn() -> Result<E>,
+{
+    let (connection, io_threads) = Connection::stdio();
+    run(connection, environment, environment_files)?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+fn run<E, F>(connection: Connection, environment: F, environment_files: Vec<PathBuf>) -> Result<()>
+where
+    E: EnvironmentProvider,
+    F: Fn() -> Result<E>,
+{
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    let mut server = Server {
+        connection: &connection,
+        documents: HashMap::new(),
+        environment,
+        environment_files,
+    };
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                let response = server.request(request);
+                connection.sender.send(response.into())?;
+            }
+            Message::Notification(notification) => server.notification(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["{".to_owned(), "$".to_owned(), ".".to_owned()]),
+            ..Default::default()
+        }),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        code_lens_provider: Some(CodeLensOptions {
+            resolve_provider: Some(false),
+        }),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![RUN_COMMAND.to_owned()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+struct Server<'a, F> {
+    connection: &'a Connection,
+    /// Contents of the open documents, which might be unsaved
+    documents: HashMap<Url, String>,
+    environment: F,
+    environment_files: Vec<PathBuf>,
+}
+
+impl<E, F> Server<'_, F>
+where
+    E: EnvironmentProvider,
+    F: Fn() -> Result<E>,
+{
+    fn request(&mut self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            <Completion as lsp_types::request::Request>::METHOD => {
+                params(request).and_then(|params| self.completion(params))
+            }
+            <HoverRequest as lsp_types::request::Request>::METHOD => {
+                params(request).and_then(|params| self.hover(params))
+            }
+            <GotoDefinition as lsp_types::request::Request>::METHOD => {
+                params(request).and_then(|params| self.definition(params))
+            }
+            <CodeLensRequest as lsp_types::request::Request>::METHOD => {
+                params(request).and_then(|params| self.code_lenses(params))
+            }
+            <ExecuteCommand as lsp_types::request::Request>::METHOD => {
+                params(request).and_then(|params| self.execute_command(params))
+            }
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unknown method `{method}`"),
+                )
+            }
+        };
+
+        match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(error) => {
+                Response::new_err(id, ErrorCode::RequestFailed as i32, format!("{error:#}"))
+            }
+        }
+    }
+
+    fn notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.documents.insert(document.uri.clone(), document.text);
+                self.publish_diagnostics(document.uri)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                self.publish_diagnostics(uri)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.notify::<PublishDiagnostics>(PublishDiagnosticsParams::new(uri, vec![], None))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: Url) -> Result<()> {
+        let diagnostics = analysis::diagnostics(&path(&uri), self.document(&uri)?);
+        self.notify::<PublishDiagnostics>(PublishDiagnosticsParams::new(uri, diagnostics, None))
+    }
+
+    fn completion(&self, params: CompletionParams) -> Result<Value> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        // the environment files might be missing or broken, while the request file is edited
+        let environment: Vec<String> = (self.environment)()
+            .ok()
+            .and_then(|environment| match environment.snapshot() {
+                Value::Object(snapshot) => Some(snapshot.keys().cloned().collect()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let items = analysis::completion(self.document(&uri)?, position.position, &environment);
+        Ok(serde_json::to_value(items)?)
+    }
+
+    fn hover(&self, params: HoverParams) -> Result<Value> {
+        let position = params.text_document_position_params;
+        let document = self.document(&position.text_document.uri)?;
+
+        Ok(serde_json::to_value(analysis::hover(
+            document,
+            position.position,
+        ))?)
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Result<Value> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+
+        let location = analysis::definition(
+            &uri,
+            self.document(&uri)?,
+            position.position,
+            &self.environment_files,
+        );
+        Ok(serde_json::to_value(location)?)
+    }
+
+    fn code_lenses(&self, params: CodeLensParams) -> Result<Value> {
+        let uri = params.text_document.uri;
+
+        let lenses = analysis::code_lenses(&uri, &path(&uri), self.document(&uri)?);
+        Ok(serde_json::to_value(lenses)?)
+    }
+
+    /// Runs a request, the output is logged and returned
+    fn execute_command(&self, params: ExecuteCommandParams) -> Result<Value> {
+        if params.command != RUN_COMMAND {
+            return Err(anyhow!("Unknown command `{}`", params.command));
+        }
+        let [uri, index] = params.arguments.as_slice() else {
+            return Err(anyhow!(
+                "Expected `{RUN_COMMAND}` arguments to be a document and a request number"
+            ));
+        };
+        let uri: Url = serde_json::from_value(uri.clone())?;
+        let index: usize = serde_json::from_value(index.clone())?;
+
+        let source = match self.documents.get(&uri) {
+            Some(document) => document.clone(),
+            None => fs::read_to_string(path(&uri))
+                .with_context(|| format!("Failed opening script file: `{uri}`"))?,
+        };
+        let output = self.run_request(&uri, &source, index)?;
+
+        self.notify::<LogMessage>(LogMessageParams {
+            typ: MessageType::INFO,
+            message: output.clone(),
+        })?;
+        Ok(Value::String(output))
+    }
+
+    fn run_request(&self, uri: &Url, source: &str, index: usize) -> Result<String> {
+        let requests = FileSourceProvider::from_source(path(uri), source, Some(index))?;
+        let mut environment = (self.environment)()?;
+
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+        let mut output = FormattedOutput::new(
+            &mut stdout,
+            &mut stderr,
+            parse_format(REQUEST_FORMAT)?,
+            parse_format(RESPONSE_FORMAT)?,
+        );
+        let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default())?;
+
+        // the server is synchronous, each request gets a runtime of its own
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(runtime.execute(requests))?;
+
+        Ok(format!(
+            "{}{}",
+            String::from_utf8_lossy(&stdout),
+            String::from_utf8_lossy(&stderr)
+        ))
+    }
+
+    fn document(&self, uri: &Url) -> Result<&str> {
+        self.documents
+            .get(uri)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("Document `{uri}` is not open"))
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) -> Result<()> {
+        let notification = Notification::new(N::METHOD.to_owned(), params);
+        self.connection.sender.send(notification.into())?;
+        Ok(())
+    }
+}
+
+fn params<P: DeserializeOwned>(request: Request) -> Result<P> {
+    Ok(serde_json::from_value(request.params)?)
+}
+
+fn path(uri: &Url) -> PathBuf {
+    uri.to_file_path()
+        .unwrap_or_else(|_| PathBuf::from(uri.path()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use lsp_types::{
+        notification::Initialized, request::Initialize, ClientCapabilities, InitializeParams,
+        InitializedParams, TextDocumentItem,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::StaticEnvironmentProvider;
+
+    #[test]
+    fn diagnostics_are_published() {
+        let (server, client) = Connection::memory();
+        let server = thread::spawn(move || {
+            run(
+                server,
+                || Ok(StaticEnvironmentProvider::new(json!({}))),
+                vec![],
+            )
+        });
+
+        client
+            .sender
+            .send(
+                Request::new(
+                    0.into(),
+                    <Initialize as lsp_types::request::Request>::METHOD.to_owned(),
+                    InitializeParams {
+                        capabilities: ClientCapabilities::default(),
+                        ..Default::default()
+                    },
+                )
+                .into(),
+            )
+            .unwrap();
+        let Message::Response(response) = client.receiver.recv().unwrap() else {
+            panic!("Expected initialize response")
+        };
+        assert!(response.error.is_none());
+        client
+            .sender
+            .send(Notification::new(Initialized::METHOD.to_owned(), InitializedParams {}).into())
+            .unwrap();
+
+        let uri = Url::parse("file:///test.http").unwrap();
+        client
+            .sender
+            .send(
+                Notification::new(
+                    DidOpenTextDocument::METHOD.to_owned(),
+                    DidOpenTextDocumentParams {
+                        text_document: TextDocumentItem::new(
+                            uri.clone(),
+                            "http".to_owned(),
+                            1,
+                            "GET http://localhost/{{id\n".to_owned(),
+                        ),
+                    },
+                )
+                .into(),
+            )
+            .unwrap();
+        let Message::Notification(notification) = client.receiver.recv().unwrap() else {
+            panic!("Expected diagnostics")
+        };
+        let params: PublishDiagnosticsParams = serde_json::from_value(notification.params).unwrap();
+        assert_eq!(params.uri, uri);
+        assert_eq!(params.diagnostics.len(), 1);
+
+        client
+            .sender
+            .send(Request::new(1.into(), "shutdown".to_owned(), ()).into())
+            .unwrap();
+        client.receiver.recv().unwrap();
+        client
+            .sender
+            .send(Notification::new("exit".to_owned(), ()).into())
+            .unwrap();
+        server.join().unwrap().unwrap();
+    }
+}
@@ -1,12 +1,13 @@
 use std::{
+    fs,
     io::{stderr, stdout},
     path::PathBuf,
 };
 
-use clap::{Args, Parser, Subcommand, ValueEnum};
-use color_eyre::Result;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use color_eyre::{eyre::WrapErr, Result};
 use dothttp::{
-    export,
+    export, format, lsp,
     output::{parse_format, print::FormattedOutput, CiOutput, Output},
     source::FilesSourceProvider,
     ClientConfig, EnvironmentFileProvider, Runtime, SourceProvider,
@@ -16,6 +17,8 @@ use dothttp::{
 #[command(author, version, about, long_about = None)]
 #[command(args_conflicts_with_subcommands = true)]
 #[command(flatten_help = true)]
+// `-V` sets variables, as in ijhttp
+#[command(disable_version_flag = true)]
 struct CliArgs {
     #[command(subcommand)]
     command: Option<Command>,
@@ -25,6 +28,10 @@ struct CliArgs {
 
     #[command(flatten)]
     env: EnvironmentArgs,
+
+    /// Print version
+    #[arg(long, action = ArgAction::Version)]
+    version: Option<bool>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -57,14 +64,34 @@ enum Command {
         /// Example: request.http request-2.http request-3.http:2
         files: Vec<String>,
     },
+    /// Rewrite request files into the canonical layout
+    Fmt {
+        /// Do not write the files, fail if any of them is not formatted
+        #[arg(long)]
+        check: bool,
+
+        /// List of request files to format
+        files: Vec<PathBuf>,
+    },
+    /// Run the language server on stdin and stdout
+    Lsp {
+        #[command(flatten)]
+        env: EnvironmentArgs,
+    },
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Args)]
 struct EnvironmentArgs {
     /// A file containing a JSON object that describes the initial values for variables
     #[arg(short = 'n', long)]
     environment_file: Option<PathBuf>,
 
+    /// A file containing a JSON object with private values for variables, such as secrets,
+    /// that override the environment file.
+    /// Defaults to the `.private.env.json` file next to the environment file
+    #[arg(long)]
+    private_env_file: Option<PathBuf>,
+
     /// A file containing a JSON object that persists variables between each invocation
     #[arg(short = 'p', long)]
     snapshot: Option<PathBuf>,
@@ -72,6 +99,26 @@ struct EnvironmentArgs {
     /// The key value to use on the environment file
     #[arg(short, long)]
     environment: Option<String>,
+
+    /// A variable overriding the environment files, can be repeated
+    ///
+    /// Example: -V build_id=42 -V token=secret
+    #[arg(short = 'V', long = "env-variables", value_name = "NAME=VALUE", value_parser = parse_variable)]
+    variables: Vec<(String, String)>,
+
+    /// A file containing a JSON object with variables overriding the environment files.
+    /// Variables given with `-V` take precedence over it
+    #[arg(long)]
+    env_vars_file: Option<PathBuf>,
+}
+
+fn parse_variable(variable: &str) -> Result<(String, String), String> {
+    match variable.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_owned(), value.to_owned()))
+        }
+        _ => Err(format!("expected `NAME=VALUE`, got `{variable}`")),
+    }
 }
 
 #[derive(Debug, Args)]
@@ -99,6 +146,15 @@ struct ExecuteArgs {
     #[arg(long = "accept-invalid-certs")]
     accept_invalid_cert: bool,
 
+    /// A file that persists cookies between each invocation
+    #[arg(long)]
+    cookie_jar: Option<PathBuf>,
+
+    /// Fail before sending a request that uses variables which are not defined,
+    /// instead of sending their `{{name}}` as it is
+    #[arg(long)]
+    strict_variables: bool,
+
     /// Which mode to use to print result.
     #[arg(long = "format", default_value = "standard")]
     format: FormatType,
@@ -121,7 +177,9 @@ enum FormatType {
 async fn main() -> Result<std::process::ExitCode> {
     color_eyre::install()?;
 
-    let CliArgs { command, exec, env } = CliArgs::parse();
+    let CliArgs {
+        command, exec, env, ..
+    } = CliArgs::parse();
 
     let command = command.unwrap_or(Command::Execute { exec, env });
 
@@ -133,22 +191,67 @@ async fn main() -> Result<std::process::ExitCode> {
         Command::ExportCollection { files, name } => {
             export::collection(name, FilesSourceProvider::from_list(&files)?.requests())?;
         }
+        Command::Fmt { check, files } => return run_fmt(files, check),
+        Command::Lsp { env } => {
+            let (env_file, private_env_file) = environment_files(&env);
+            tokio::task::spawn_blocking(move || {
+                lsp::serve(
+                    || environment(env.clone()),
+                    vec![env_file, private_env_file],
+                )
+            })
+            .await??;
+        }
     }
 
     Ok(std::process::ExitCode::SUCCESS)
 }
 
-fn environment(
-    EnvironmentArgs {
-        environment_file,
+/// Environment file and the private one
+fn environment_files(args: &EnvironmentArgs) -> (PathBuf, PathBuf) {
+    let env_file = args
+        .environment_file
+        .clone()
+        .unwrap_or_else(|| "http-client.env.json".into());
+    let private_env_file = args
+        .private_env_file
+        .clone()
+        .unwrap_or_else(|| EnvironmentFileProvider::private_environment_path(&env_file));
+
+    (env_file, private_env_file)
+}
+
+fn environment(args: EnvironmentArgs) -> Result<EnvironmentFileProvider> {
+    let (env_file, private_env_file) = environment_files(&args);
+    let EnvironmentArgs {
         snapshot,
         environment,
-    }: EnvironmentArgs,
-) -> Result<EnvironmentFileProvider> {
+        variables,
+        env_vars_file,
+        ..
+    } = args;
     let env = environment.unwrap_or("dev".to_owned());
-    let env_file = environment_file.unwrap_or_else(|| "http-client.env.json".into());
     let snapshot_file = snapshot.unwrap_or_else(|| ".snapshot.json".into());
-    EnvironmentFileProvider::open(&env, &env_file, &snapshot_file)
+    let provider =
+        EnvironmentFileProvider::open(&env, &env_file, &private_env_file, &snapshot_file)?;
+
+    let file_variables = match env_vars_file {
+        Some(path) => {
+            let content =
+                fs::read(&path).wrap_err_with(|| format!("Failed reading `{}`", path.display()))?;
+            serde_json::from_slice(&content)
+                .wrap_err_with(|| format!("Expected `{}` to be a JSON object", path.display()))?
+        }
+        None => serde_json::Map::new(),
+    };
+    let variables = variables
+        .into_iter()
+        .map(|(name, value)| (name, value.into()))
+        .collect();
+
+    Ok(provider
+        .with_variables(file_variables)
+        .with_variables(variables))
 }
 
 async fn run_execute(
@@ -159,25 +262,62 @@ async fn run_execute(
         request_format,
         response_format,
         accept_invalid_cert,
+        cookie_jar,
+        strict_variables,
         format,
         files,
     } = args;
 
     let ignore_certificates: bool = accept_invalid_cert;
 
-    let client_config = ClientConfig::new(!ignore_certificates);
+    let client_config = ClientConfig {
+        cookie_jar,
+        ..ClientConfig::new(!ignore_certificates)
+    };
 
     let mut output = get_output(format, request_format, response_format)?;
 
-    let mut runtime = Runtime::new(&mut environment, &mut output, client_config).unwrap();
+    let mut runtime = Runtime::new(&mut environment, &mut output, client_config)
+        .unwrap()
+        .with_strict_variables(strict_variables);
+
+    let source = FilesSourceProvider::from_list(&files)?;
+    for warning in source.warnings() {
+        eprintln!("{warning}\n");
+    }
 
-    runtime
-        .execute(FilesSourceProvider::from_list(&files)?)
-        .await?;
+    runtime.execute(source).await?;
 
     Ok(output.exit_code())
 }
 
+fn run_fmt(files: Vec<PathBuf>, check: bool) -> Result<std::process::ExitCode> {
+    let mut unformatted = false;
+
+    for file in files {
+        let source = fs::read_to_string(&file)
+            .wrap_err_with(|| format!("Failed opening script file: `{}`", file.display()))?;
+        let formatted = format::format(file.clone(), &source)
+            .wrap_err_with(|| format!("Failed parsing file: `{}`", file.display()))?;
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            eprintln!("`{}` is not formatted", file.display());
+            unformatted = true;
+        } else {
+            fs::write(&file, formatted)
+                .wrap_err_with(|| format!("Failed writing `{}`", file.display()))?;
+        }
+    }
+
+    Ok(match unformatted {
+        true => std::process::ExitCode::FAILURE,
+        false => std::process::ExitCode::SUCCESS,
+    })
+}
+
 fn get_output(
     ty: FormatType,
     request_format: String,
@@ -3,7 +3,7 @@ use std::process::ExitCode;
 use ascii_table::{Align, AsciiTable};
 
 use crate::{
-    http::{Request, Response},
+    http::{Direction, Message, Request, Response},
     output::Output,
     script_engine::report::TestsReport,
 };
@@ -22,6 +22,10 @@ impl Output for CiOutput {
         Ok(())
     }
 
+    fn message(&mut self, _message: &Message, _direction: Direction) -> crate::Result<()> {
+        Ok(())
+    }
+
     fn tests(&mut self, tests: Vec<(String, String, TestsReport)>) -> crate::Result<()> {
         let mut ascii_table = AsciiTable::default();
         ascii_table.set_max_width(256);
@@ -10,7 +10,7 @@ use color_eyre::eyre::anyhow;
 
 pub use self::{ci::CiOutput, print::FormattedOutput};
 use crate::{
-    http::{Method, Request, Response},
+    http::{Direction, Message, Method, Request, Response},
     script_engine::report::TestsReport,
     Result,
 };
@@ -71,9 +71,48 @@ fn prettify_response_body(body: &str) -> String {
     }
 }
 
+/// Size of the binary body followed by a hexdump of its first bytes
+fn summarize_binary_body(data: &[u8]) -> String {
+    use std::fmt::Write;
+
+    const LIMIT: usize = 256;
+
+    let mut output = format!("<binary body: {} bytes>\n", data.len());
+    for (index, line) in data.chunks(16).take(LIMIT / 16).enumerate() {
+        write!(&mut output, "{:08x} ", index * 16).unwrap();
+        for column in 0..16 {
+            if column == 8 {
+                output.push(' ');
+            }
+            match line.get(column) {
+                Some(byte) => write!(&mut output, " {byte:02x}").unwrap(),
+                None => output.push_str("   "),
+            }
+        }
+        let printable = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        writeln!(&mut output, "  |{printable}|").unwrap();
+    }
+    if data.len() > LIMIT {
+        writeln!(&mut output, "...").unwrap();
+    }
+
+    output
+}
+
 pub trait Output {
     fn response(&mut self, response: &Response, tests: &TestsReport) -> Result<()>;
     fn request(&mut self, request: &Request, request_name: &str) -> Result<()>;
+    /// Called for each message of a duplex connection as soon as it is sent or received
+    fn message(&mut self, message: &Message, direction: Direction) -> Result<()>;
     fn tests(&mut self, tests: Vec<(String, String, TestsReport)>) -> Result<()>;
 
     fn exit_code(&mut self) -> std::process::ExitCode {
@@ -90,6 +129,10 @@ impl Output for Box<dyn Output> {
         (**self).request(request, request_name)
     }
 
+    fn message(&mut self, message: &Message, direction: Direction) -> Result<()> {
+        (**self).message(message, direction)
+    }
+
     fn tests(&mut self, tests: Vec<(String, String, TestsReport)>) -> Result<()> {
         (**self).tests(tests)
     }
@@ -101,13 +144,19 @@ impl Output for Box<dyn Output> {
 
 impl fmt::Display for Method {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let method = match *self {
+        let method = match self {
             Method::Get => "GET",
             Method::Post => "POST",
             Method::Delete => "DELETE",
             Method::Put => "PUT",
             Method::Patch => "PATCH",
             Method::Options => "OPTIONS",
+            Method::Head => "HEAD",
+            Method::Trace => "TRACE",
+            Method::Connect => "CONNECT",
+            Method::Websocket => "WEBSOCKET",
+            Method::Grpc => "GRPC",
+            Method::Custom(method) => method,
         };
         f.write_str(method)
     }
@@ -2,7 +2,7 @@ use std::{fmt, io::Write, process::ExitCode};
 
 use crate::{
     http,
-    output::{prettify_response_body, FormatItem, Output},
+    output::{prettify_response_body, summarize_binary_body, FormatItem, Output},
     script_engine::report::{TestResult, TestsReport},
     Result,
 };
@@ -44,13 +44,41 @@ fn format_headers(headers: &[(String, String)]) -> String {
     })
 }
 
-fn format_body(body: &Option<String>) -> String {
+fn format_response_body(response: &http::Response) -> String {
+    match (response.text(), &response.body) {
+        (Some(body), _) => prettify_response_body(&body),
+        (None, Some(data)) => summarize_binary_body(data),
+        (None, None) => String::from(""),
+    }
+}
+
+fn format_request_body(body: &Option<http::Body>) -> String {
     match body {
-        Some(body) => prettify_response_body(body),
+        Some(body) => format_http_body(body),
         None => String::from(""),
     }
 }
 
+fn format_http_body(body: &http::Body) -> String {
+    use std::fmt::Write;
+
+    match body {
+        http::Body::Text(body) => prettify_response_body(body),
+        http::Body::Binary(data) => summarize_binary_body(data),
+        http::Body::Multipart { boundary, parts } => {
+            let mut output = String::new();
+            for http::Part { headers, body } in parts {
+                writeln!(&mut output, "--{boundary}").unwrap();
+                write!(&mut output, "{}", format_headers(headers)).unwrap();
+                writeln!(&mut output).unwrap();
+                writeln!(&mut output, "{}", format_http_body(body)).unwrap();
+            }
+            write!(&mut output, "--{boundary}--").unwrap();
+            output
+        }
+    }
+}
+
 fn format_tests(report: &TestsReport) -> String {
     use std::fmt::Write;
 
@@ -80,7 +108,6 @@ impl<W1: Write, W2: Write> Output for FormattedOutput<W1, W2> {
             headers,
             version,
             status,
-            body,
             ..
         } = response;
 
@@ -88,7 +115,7 @@ impl<W1: Write, W2: Write> Output for FormattedOutput<W1, W2> {
             let to_write = match format_item {
                 FormatItem::FirstLine => format!("{} {}", version, status),
                 FormatItem::Headers => format_headers(headers),
-                FormatItem::Body => format_body(body),
+                FormatItem::Body => format_response_body(response),
                 FormatItem::Chars(s) => s.clone(),
                 FormatItem::Tests => format_tests(tests),
                 FormatItem::Name => continue,
@@ -122,7 +149,7 @@ impl<W1: Write, W2: Write> Output for FormattedOutput<W1, W2> {
             let to_write = match format_item {
                 FormatItem::FirstLine => format!("{} {}", method, target),
                 FormatItem::Headers => format_headers(headers),
-                FormatItem::Body => format_body(body),
+                FormatItem::Body => format_request_body(body),
                 FormatItem::Chars(s) => s.clone(),
                 FormatItem::Tests => continue,
                 FormatItem::Name => format!("[{request_name}]"),
@@ -137,6 +164,24 @@ impl<W1: Write, W2: Write> Output for FormattedOutput<W1, W2> {
         Ok(())
     }
 
+    fn message(&mut self, message: &http::Message, direction: http::Direction) -> Result<()> {
+        if self.response_format.is_empty() {
+            return Ok(());
+        }
+
+        let arrow = match direction {
+            http::Direction::Sent => "=>",
+            http::Direction::Received => "<=",
+        };
+        let message = match message {
+            http::Message::Text(text) => prettify_response_body(text),
+            http::Message::Binary(data) => summarize_binary_body(data),
+        };
+
+        writeln!(self.writer, "{arrow} {message}")?;
+        Ok(())
+    }
+
     fn tests(&mut self, tests: Vec<(String, String, TestsReport)>) -> Result<()> {
         if !self.error {
             return Ok(());
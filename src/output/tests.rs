@@ -1,5 +1,5 @@
 use crate::{
-    http::{Method, Request, Response, Version},
+    http::{Body, Method, Request, Response, Version},
     output::{parse_format, prettify_response_body, print::FormattedOutput, FormatItem, Output},
     script_engine::report::TestsReport,
 };
@@ -56,14 +56,16 @@ fn test_format_request() {
         method: Method::Get,
         target: "localhost:8080".to_string(),
         headers: vec![("Content-Type".to_string(), "text/json".to_string())],
-        body: Some("{\"req\":\"great\"}".to_string()),
+        body: Some(Body::Text("{\"req\":\"great\"}".to_string())),
+        options: Default::default(),
     };
     let response = Response {
         version: Version::Http11,
         status_code: 200,
         status: "200 Ok".to_string(),
         headers: vec![("Content-Type".to_string(), "text/json".to_string())],
-        body: Some("{\"resp\":\"great-resp\"}".to_string()),
+        body: Some(b"{\"resp\":\"great-resp\"}".to_vec()),
+        messages: None,
     };
     let empty_format = parse_format("").expect("valid format");
 
@@ -161,3 +163,56 @@ Content-Type: text/json
         "GET localhost:8080\nHTTP/1.1 200 Ok\n"
     );
 }
+
+#[test]
+fn test_format_binary_response() {
+    let response = Response {
+        version: Version::Http11,
+        status_code: 200,
+        status: "200 Ok".to_string(),
+        headers: vec![("Content-Type".to_string(), "image/png".to_string())],
+        body: Some(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01".to_vec()),
+        messages: None,
+    };
+
+    let format = parse_format("%B").expect("valid format");
+    let mut buffer = Vec::new();
+    let mut err_buffer = Vec::new();
+    let mut outputter = FormattedOutput::new(&mut buffer, &mut err_buffer, format.clone(), format);
+    outputter
+        .response(&response, &TestsReport::default())
+        .expect("print works correctly");
+
+    assert_eq!(
+        String::from_utf8(buffer).expect("is a string"),
+        "<binary body: 20 bytes>
+00000000  89 50 4e 47 0d 0a 1a 0a  00 00 00 0d 49 48 44 52  |.PNG........IHDR|
+00000010  00 00 00 01                                       |....|
+"
+    );
+}
+
+#[test]
+fn test_format_response_with_charset() {
+    let response = Response {
+        version: Version::Http11,
+        status_code: 200,
+        status: "200 Ok".to_string(),
+        headers: vec![(
+            "Content-Type".to_string(),
+            "text/plain; charset=ISO-8859-1".to_string(),
+        )],
+        body: Some(b"caf\xe9".to_vec()),
+        messages: None,
+    };
+
+    let format = parse_format("%B").expect("valid format");
+    let mut buffer = Vec::new();
+    let mut err_buffer = Vec::new();
+    let mut outputter = FormattedOutput::new(&mut buffer, &mut err_buffer, format.clone(), format);
+    outputter
+        .response(&response, &TestsReport::default())
+        .expect("print works correctly");
+
+    assert_eq!(String::from_utf8(buffer).expect("is a string"), "café");
+}
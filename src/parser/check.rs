@@ -0,0 +1,174 @@
+use crate::parser::{
+    diagnostic::{Diagnostic, Severity},
+    Body, File, Position, Selection, Value,
+};
+
+/// Mistakes the grammar accepts, but that are not what was meant:
+/// a `> {%` without `%}` is parsed as the target of the next request,
+/// and a `{{` without `}}` or a header after a blank line are taken as text.
+/// The latter two may as well be intended, so they are only warnings
+pub fn file(file: &File, source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    for script in &file.request_scripts {
+        let request = &script.request;
+        if request.target.state.value().trim().is_empty() {
+            diagnostics.push(
+                Diagnostic::new(
+                    "expected a request target",
+                    selection_at(&request.target, 0, 0),
+                    source,
+                )
+                .with_hint("add the URL to the request line, like `GET https://example.com`"),
+            );
+            continue;
+        }
+
+        if request.target.state.value().starts_with("###") {
+            diagnostics.push(
+                Diagnostic::new(
+                    "expected a request line before `###`",
+                    selection_at(&request.target, 0, 3),
+                    source,
+                )
+                .with_hint(
+                    "variables and directives must be followed by a request, like `GET https://example.com`",
+                ),
+            );
+            continue;
+        }
+
+        if let Some(offset) = request.target.state.value().find("{%") {
+            diagnostics.push(
+                Diagnostic::new(
+                    "unterminated `{%`",
+                    selection_at(&request.target, offset, 2),
+                    source,
+                )
+                .with_hint("close the script with `%}`"),
+            );
+            continue;
+        }
+
+        let body = match &request.body {
+            Some(Body::Inline(body)) => Some(body),
+            _ => None,
+        };
+        let values = script
+            .request_variables
+            .iter()
+            .map(|(_, value)| value)
+            .chain([&request.target])
+            .chain(request.headers.iter().map(|header| &header.field_value))
+            .chain(body);
+        for value in values {
+            if let Some(offset) = unterminated_inline_script(value) {
+                diagnostics.push(
+                    Diagnostic::new("unterminated `{{`", selection_at(value, offset, 2), source)
+                        .with_severity(Severity::Warning)
+                        .with_hint("close the variable with `}}`"),
+                );
+            }
+        }
+
+        // headers are valid YAML, text and so on, but not JSON
+        let content_type = request
+            .headers
+            .iter()
+            .find(|header| header.field_name.eq_ignore_ascii_case("content-type"))
+            .map(|header| header.field_value.state.value());
+        let expects_headers = content_type.map_or(true, |it| it.contains("json"));
+        if let Some(body) = body.filter(|_| expects_headers) {
+            if let Some((name, offset)) = header_after_body(body.state.value()) {
+                diagnostics.push(
+                    Diagnostic::new(
+                        format!("`{name}` header is sent as a part of the body"),
+                        selection_at(body, offset, name.len()),
+                        source,
+                    )
+                    .with_severity(Severity::Warning)
+                    .with_hint(
+                        "header line after body: remove the blank line between the headers and it",
+                    ),
+                );
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Offset of a `{{` that does not start an inline script and is not closed with `}}`,
+/// so that text like `{{#each items}}` is sent as it is
+fn unterminated_inline_script(value: &Value) -> Option<usize> {
+    let text = value.state.value();
+    let mut inline_scripts = vec![];
+    let mut from = 0;
+    for inline_script in value.state.inline_scripts() {
+        if let Some(start) = text[from..].find(&inline_script.placeholder) {
+            let start = from + start;
+            from = start + inline_script.placeholder.len();
+            inline_scripts.push(start..from);
+        }
+    }
+
+    text.match_indices("{{")
+        .map(|(offset, _)| offset)
+        .find(|offset| {
+            !inline_scripts.iter().any(|range| range.contains(offset))
+                && !text[offset + 2..].contains("}}")
+        })
+}
+
+/// Name and offset of the first header in a body that starts with headers, a blank line
+/// and something else, meaning that the headers were separated from the request by mistake
+fn header_after_body(body: &str) -> Option<(&str, usize)> {
+    let mut lines = body.lines();
+    let headers = lines
+        .by_ref()
+        .take_while(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+
+    let name = headers.first()?.split_once(':')?.0;
+    let all_headers = headers.iter().all(|line| match line.split_once(':') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        }
+        None => false,
+    });
+    if !all_headers || !lines.any(|line| !line.trim().is_empty()) {
+        return None;
+    }
+
+    let offset = body.find(name)?;
+    Some((name, offset))
+}
+
+/// Selection of `len` characters at `offset` of the value
+fn selection_at(value: &Value, offset: usize, len: usize) -> Selection {
+    let selection = value.state.selection();
+    let before = &value.state.value()[..offset];
+    let start = match before.rfind('\n') {
+        Some(newline) => Position {
+            line: selection.start.line + before.matches('\n').count(),
+            col: before[newline + 1..].chars().count() + 1,
+        },
+        None => Position {
+            line: selection.start.line,
+            col: selection.start.col + before.chars().count(),
+        },
+    };
+    let end = Position {
+        line: start.line,
+        col: start.col + len,
+    };
+
+    Selection {
+        filename: selection.filename.clone(),
+        start,
+        end,
+    }
+}
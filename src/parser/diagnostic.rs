@@ -0,0 +1,135 @@
+use std::{
+    fmt,
+    fmt::{Display, Formatter},
+};
+
+use crate::parser::{Position, Selection};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// The file cannot be executed
+    Error,
+    /// The file is executed, but probably not the way it was meant to
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// Problem in a `.http` file, displayed the way `rustc` does:
+///
+/// ```text
+/// error: unterminated `{%`
+///  --> requests.http:7:3
+///   |
+/// 7 | > {% client.test("status", () => {
+///   |   ^^
+///   = hint: close the script with `%}`
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub selection: Selection,
+    pub hint: Option<String>,
+    /// Line of the file the selection starts at
+    snippet: String,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, selection: Selection, source: &str) -> Self {
+        let snippet = source
+            .lines()
+            .nth(selection.start.line.saturating_sub(1))
+            .unwrap_or_default()
+            .to_owned();
+
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            selection,
+            hint: None,
+            snippet,
+        }
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Selection {
+            filename,
+            start: Position { line, col },
+            end,
+        } = &self.selection;
+        let gutter = " ".repeat(line.to_string().len());
+
+        writeln!(f, "{}: {}", self.severity, self.message)?;
+        writeln!(f, "{gutter}--> {}:{line}:{col}", filename.display())?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{line} | {}", self.snippet)?;
+
+        let width = self.snippet.chars().count();
+        let end = if end.line == *line {
+            end.col
+        } else {
+            width + 1
+        };
+        let underline = "^".repeat(end.saturating_sub(*col).max(1));
+        write!(
+            f,
+            "{gutter} | {}{underline}",
+            " ".repeat(col.saturating_sub(1))
+        )?;
+
+        if let Some(hint) = &self.hint {
+            write!(f, "\n{gutter} = hint: {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let source = "GET http://localhost\n\n> {% client.log(1)\n";
+        let selection = Selection {
+            filename: "requests.http".into(),
+            start: Position { line: 3, col: 3 },
+            end: Position { line: 3, col: 5 },
+        };
+
+        let diagnostic = Diagnostic::new("unterminated `{%`", selection, source)
+            .with_hint("close the script with `%}`");
+
+        assert_eq!(
+            diagnostic.to_string(),
+            "\
+error: unterminated `{%`
+ --> requests.http:3:3
+  |
+3 | > {% client.log(1)
+  |   ^^
+  = hint: close the script with `%}`"
+        );
+    }
+}
@@ -1,16 +1,26 @@
+mod check;
+pub mod diagnostic;
+pub mod multipart;
 #[cfg(test)]
 pub mod tests;
 
 use std::{
     error, fmt,
     fmt::{Display, Formatter},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use pest::{error::LineColLocation, iterators::Pair, Parser, Span};
+use pest::{
+    error::{ErrorVariant, LineColLocation},
+    iterators::Pair,
+    Parser, Span,
+};
 use pest_derive::Parser;
 
-use crate::Result;
+use crate::{
+    parser::diagnostic::{Diagnostic, Severity},
+    Result,
+};
 
 macro_rules! find_rule {
     ($pairs: expr, $rule: pat) => {
@@ -25,18 +35,23 @@ macro_rules! find_rule {
 #[grammar = "parser/parser.pest"]
 struct ScriptParser;
 
+/// All of the problems found in a file
 #[derive(Debug)]
 pub struct Error {
-    pub message: String,
-    #[allow(unused)]
-    pub selection: Selection,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl error::Error for Error {}
 
 impl Display for Error {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
-        fmt.write_str(self.message.as_str())
+        for (index, diagnostic) in self.diagnostics.iter().enumerate() {
+            if index > 0 {
+                fmt.write_str("\n\n")?;
+            }
+            write!(fmt, "{diagnostic}")?;
+        }
+        Ok(())
     }
 }
 
@@ -106,7 +121,13 @@ impl FromPair for Method {
                 "PUT" => Method::Put(selection),
                 "PATCH" => Method::Patch(selection),
                 "OPTIONS" => Method::Options(selection),
-                _ => panic!("Unsupported method: {}", pair.as_str()),
+                "HEAD" => Method::Head(selection),
+                "TRACE" => Method::Trace(selection),
+                "CONNECT" => Method::Connect(selection),
+                "GRAPHQL" => Method::Graphql(selection),
+                "WEBSOCKET" => Method::Websocket(selection),
+                "GRPC" => Method::Grpc(selection),
+                other => Method::Custom(other.to_owned(), selection),
             },
             _ => invalid_pair(Rule::method, pair.as_rule()),
         }
@@ -120,7 +141,14 @@ impl FromPair for Value {
                 Rule::request_target
                 | Rule::field_value
                 | Rule::request_body
-                | Rule::request_variable_value,
+                | Rule::graphql_query
+                | Rule::graphql_variables
+                | Rule::websocket_message
+                | Rule::request_body_file_path
+                | Rule::response_redirect_path
+                | Rule::template
+                | Rule::request_variable_value
+                | Rule::run_override_value,
                 string,
             ) => {
                 let selection = pair.as_span().to_selection(filename.clone());
@@ -152,16 +180,19 @@ impl FromPair for Value {
 impl FromPair for InlineScript {
     fn from_pair(filename: PathBuf, pair: Pair<'_, Rule>) -> Self {
         match pair.as_rule() {
-            Rule::inline_script => InlineScript {
-                selection: pair.as_span().to_selection(filename),
-                placeholder: pair.as_str().to_string(),
-                script: pair
-                    .into_inner()
-                    .map(|pair| pair.as_str())
-                    .last()
-                    .unwrap()
-                    .to_string(),
-            },
+            Rule::inline_script => {
+                let selection = pair.as_span().to_selection(filename);
+                let placeholder = pair.as_str().to_string();
+                let script = pair.into_inner().map(|pair| pair.as_str()).last().unwrap();
+                let (script, raw) = strip_raw(script);
+
+                InlineScript {
+                    selection,
+                    placeholder,
+                    script: script.to_string(),
+                    raw,
+                }
+            }
             _ => invalid_pair(Rule::inline_script, pair.as_rule()),
         }
     }
@@ -191,6 +222,75 @@ impl FromPair for Header {
     }
 }
 
+impl FromPair for Body {
+    fn from_pair(filename: PathBuf, pair: Pair<'_, Rule>) -> Self {
+        match pair.as_rule() {
+            Rule::request_body => Body::Inline(Value::from_pair(filename, pair)),
+            Rule::request_body_file => {
+                let selection = pair.as_span().to_selection(filename.clone());
+                let mut pairs = pair.into_inner();
+                let kind = find_rule!(pairs, Rule::request_body_file_kind).unwrap();
+                let path = find_rule!(pairs, Rule::request_body_file_path).unwrap();
+
+                Body::File {
+                    process_variables: kind.as_str() == "<@",
+                    path: Value::from_pair(filename, path),
+                    selection,
+                }
+            }
+            Rule::graphql_body => {
+                let selection = pair.as_span().to_selection(filename.clone());
+                let mut pairs = pair.into_inner();
+                let query = find_rule!(pairs, Rule::graphql_query).unwrap();
+                let variables = find_rule!(pairs, Rule::graphql_variables);
+
+                Body::Graphql {
+                    query: Value::from_pair(filename.clone(), query),
+                    variables: variables.map(|it| Value::from_pair(filename, it)),
+                    selection,
+                }
+            }
+            Rule::websocket_body => Body::Websocket {
+                selection: pair.as_span().to_selection(filename.clone()),
+                steps: pair
+                    .into_inner()
+                    .filter_map(|pair| match pair.as_rule() {
+                        Rule::websocket_marker => {
+                            find_rule!(pair.into_inner(), Rule::websocket_wait)
+                                .map(|_| WebsocketStep::WaitForServer)
+                        }
+                        Rule::websocket_message if !pair.as_str().trim().is_empty() => Some(
+                            WebsocketStep::Send(Value::from_pair(filename.clone(), pair)),
+                        ),
+                        _ => None,
+                    })
+                    .collect(),
+            },
+            _ => invalid_pair(Rule::request_body, pair.as_rule()),
+        }
+    }
+}
+
+impl FromPair for Redirect {
+    fn from_pair(filename: PathBuf, pair: Pair<'_, Rule>) -> Self {
+        match pair.as_rule() {
+            Rule::response_redirect => {
+                let selection = pair.as_span().to_selection(filename.clone());
+                let mut pairs = pair.into_inner();
+                let kind = find_rule!(pairs, Rule::response_redirect_kind).unwrap();
+                let path = find_rule!(pairs, Rule::response_redirect_path).unwrap();
+
+                Redirect {
+                    overwrite: kind.as_str() == ">>!",
+                    path: Value::from_pair(filename, path),
+                    selection,
+                }
+            }
+            _ => invalid_pair(Rule::response_redirect, pair.as_rule()),
+        }
+    }
+}
+
 impl FromPair for Request {
     fn from_pair(filename: PathBuf, pair: Pair<'_, Rule>) -> Self {
         match pair.as_rule() {
@@ -198,7 +298,7 @@ impl FromPair for Request {
                 let selection = pair.as_span().to_selection(filename.clone());
                 let mut pairs = pair.into_inner();
                 Request {
-                    selection,
+                    selection: selection.clone(),
                     method: pairs
                         .clone() // clone in order to be able to iterate over it again, if no method is found
                         .find_map(|pair| match pair.as_rule() {
@@ -206,12 +306,15 @@ impl FromPair for Request {
                             _ => None,
                         })
                         .unwrap_or(Method::Get(Selection::none())),
+                    // a missing target is reported by `check::file`, as is an empty one
                     target: pairs
                         .find_map(|pair| match pair.as_rule() {
                             Rule::request_target => Some(Value::from_pair(filename.clone(), pair)),
                             _ => None,
                         })
-                        .unwrap_or_else(|| panic!("Couldn't find target in request script")),
+                        .unwrap_or_else(|| Value {
+                            state: Unprocessed::WithoutInline(String::new(), selection),
+                        }),
                     headers: pairs
                         .clone()
                         .filter_map(|pair| match pair.as_rule() {
@@ -221,10 +324,13 @@ impl FromPair for Request {
                         .collect::<Vec<Header>>(),
                     body: {
                         let pair = pairs.find_map(|pair| match pair.as_rule() {
-                            Rule::request_body => Some(pair),
+                            Rule::request_body
+                            | Rule::request_body_file
+                            | Rule::graphql_body
+                            | Rule::websocket_body => Some(pair),
                             _ => None,
                         });
-                        pair.map(|pair| Value::from_pair(filename, pair))
+                        pair.map(|pair| Body::from_pair(filename, pair))
                     },
                 }
             }
@@ -235,9 +341,15 @@ impl FromPair for Request {
 
 impl FromPair for RequestScript {
     fn from_pair(filename: PathBuf, pair: Pair<'_, Rule>) -> Self {
+        let directives = directives_from_pairs(
+            pair.clone()
+                .into_inner()
+                .filter(|pair| pair.as_rule() == Rule::directives),
+        );
+
         match pair.as_rule() {
             Rule::request_script => RequestScript {
-                name: {
+                name: directives.name.clone().or_else(|| {
                     let mut pairs = pair.clone().into_inner();
                     let pair = pairs.find_map(|pair| match pair.as_rule() {
                         Rule::request_separator_with_name => Some(pair),
@@ -246,7 +358,7 @@ impl FromPair for RequestScript {
 
                     pair.map(|pair| pair.as_str().strip_prefix("###").unwrap().trim().to_owned())
                         .and_then(|it| if it.is_empty() { None } else { Some(it) })
-                },
+                }),
                 selection: pair.as_span().to_selection(filename.clone()),
                 request_variables: {
                     let declarations = find_rule!(
@@ -273,6 +385,11 @@ impl FromPair for RequestScript {
                     });
                     pair.map(|pair| Handler::from_pair(filename.clone(), pair))
                 },
+                directives,
+                redirect: {
+                    let pair = find_rule!(pair.clone().into_inner(), Rule::response_redirect);
+                    pair.map(|pair| Redirect::from_pair(filename.clone(), pair))
+                },
                 request: Request::from_pair(filename, pair),
             },
             _ => invalid_pair(Rule::request_script, pair.as_rule()),
@@ -280,6 +397,30 @@ impl FromPair for RequestScript {
     }
 }
 
+fn directives_from_pairs<'a>(pairs: impl Iterator<Item = Pair<'a, Rule>>) -> Directives {
+    let mut directives = Directives::default();
+
+    for directive in pairs.flat_map(|pair| pair.into_inner()) {
+        let mut pairs = directive.into_inner();
+        let name = find_rule!(pairs.clone(), Rule::directive_name).unwrap();
+        let value = find_rule!(pairs, Rule::directive_value).map(|it| it.as_str().to_owned());
+
+        match name.as_str() {
+            "name" => directives.name = value,
+            "no-redirect" => directives.no_redirect = true,
+            "timeout" => directives.timeout = value,
+            "connection-timeout" => directives.connection_timeout = value,
+            "no-log" => directives.no_log = true,
+            "no-cookie-jar" => directives.no_cookie_jar = true,
+            "max-events" => directives.max_events = value,
+            // unknown directives are ignored, same as comments
+            _ => {}
+        }
+    }
+
+    directives
+}
+
 fn request_variable_declaration_from_pair(
     filename: PathBuf,
     pair: Pair<'_, Rule>,
@@ -302,18 +443,126 @@ fn request_variable_declaration_from_pair(
 impl FromPair for File {
     fn from_pair(filename: PathBuf, pair: Pair<'_, Rule>) -> Self {
         match pair.as_rule() {
-            Rule::file => File {
-                request_scripts: pair
-                    .into_inner()
-                    .filter(|pair| pair.as_rule() == Rule::request_script)
-                    .map(|pair| RequestScript::from_pair(filename.clone(), pair))
-                    .collect::<Vec<RequestScript>>(),
-            },
+            Rule::file => {
+                let mut request_scripts = vec![];
+                let mut imports = vec![];
+                let mut runs = vec![];
+                for pair in pair.clone().into_inner() {
+                    match pair.as_rule() {
+                        Rule::request_script => {
+                            request_scripts.push(RequestScript::from_pair(filename.clone(), pair))
+                        }
+                        Rule::import_statement => imports.push(Import {
+                            selection: pair.as_span().to_selection(filename.clone()),
+                            path: find_rule!(pair.into_inner(), Rule::import_path)
+                                .unwrap()
+                                .as_str()
+                                .to_owned(),
+                        }),
+                        Rule::run_statement => runs.push(Run::from_pair(
+                            filename.clone(),
+                            pair,
+                            request_scripts.len(),
+                        )),
+                        _ => {}
+                    }
+                }
+                let comments = comments_from_source(filename, pair.as_str(), &request_scripts);
+
+                File {
+                    request_scripts,
+                    imports,
+                    runs,
+                    comments,
+                    warnings: vec![],
+                }
+            }
             _ => invalid_pair(Rule::file, pair.as_rule()),
         }
     }
 }
 
+impl Run {
+    fn from_pair(filename: PathBuf, pair: Pair<'_, Rule>, position: usize) -> Self {
+        let selection = pair.as_span().to_selection(filename.clone());
+        let mut pairs = pair.into_inner();
+        let target = pairs.next().expect("run_target").as_str();
+        let target = match target.strip_prefix('#') {
+            Some(name) => RunTarget::Request(name.to_owned()),
+            None => RunTarget::File(target.to_owned()),
+        };
+        let overrides = pairs
+            .map(|pair| {
+                let mut pairs = pair.into_inner();
+                let name = pairs.next().expect("request_variable_name");
+                let value = pairs.next().expect("run_override_value");
+                (
+                    name.as_str().to_owned(),
+                    Value::from_pair(filename.clone(), value),
+                )
+            })
+            .collect();
+
+        Run {
+            target,
+            overrides,
+            position,
+            selection,
+        }
+    }
+}
+
+/// Lines starting with `#`, except for the ones that are a part of a body or a script
+fn comments_from_source(
+    filename: PathBuf,
+    source: &str,
+    request_scripts: &[RequestScript],
+) -> Vec<Comment> {
+    let skipped = request_scripts
+        .iter()
+        .flat_map(|script| {
+            let body = script.request.body.as_ref().map(Body::selection);
+            let handlers = [&script.pre_request_handler, &script.handler]
+                .into_iter()
+                .flatten()
+                .map(|handler| &handler.selection);
+            [script.request.target.state.selection()]
+                .into_iter()
+                .chain(body)
+                .chain(handlers)
+        })
+        .collect::<Vec<_>>();
+
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let text = line.trim();
+            let line = index + 1;
+            let in_skipped = skipped.iter().any(|selection| {
+                selection.start.line <= line
+                    && (line < selection.end.line
+                        || line == selection.end.line && selection.end.col > 1)
+            });
+            if !text.starts_with('#') || text.starts_with("###") || in_skipped {
+                return None;
+            }
+
+            Some(Comment {
+                text: text.to_owned(),
+                selection: Selection {
+                    filename: filename.clone(),
+                    start: Position { line, col: 1 },
+                    end: Position {
+                        line,
+                        col: text.chars().count() + 1,
+                    },
+                },
+            })
+        })
+        .collect()
+}
+
 impl ToSelection for Span<'_> {
     fn to_selection(self, filename: PathBuf) -> Selection {
         let (start_line, start_col) = self.start_pos().line_col();
@@ -333,14 +582,93 @@ impl ToSelection for Span<'_> {
 }
 
 pub fn parse(filename: PathBuf, source: &str) -> Result<File> {
-    Ok(ScriptParser::parse(Rule::file, source)
-        .map_err(|error| Error {
-            message: error.to_string(),
-            selection: error.line_col.to_selection(filename.clone()),
-        })?
+    let mut file = ScriptParser::parse(Rule::file, source)
+        .map_err(|_| syntax_errors(&filename, source))?
         .map(|pair| File::from_pair(filename.clone(), pair))
         .last()
-        .unwrap())
+        .unwrap();
+
+    let diagnostics = check::file(&file, source);
+    if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        return Err(Error { diagnostics }.into());
+    }
+    file.warnings = diagnostics;
+
+    Ok(file)
+}
+
+/// Parses every request of the file on its own, so that all of the broken ones are reported
+fn syntax_errors(filename: &Path, source: &str) -> Error {
+    let mut chunks: Vec<(usize, String)> = vec![];
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        match chunks.last_mut() {
+            Some((_, chunk)) if !line.starts_with("###") => chunk.push_str(line),
+            _ => chunks.push((index, line.to_owned())),
+        }
+    }
+
+    let diagnostics = chunks
+        .into_iter()
+        .filter_map(|(first_line, chunk)| {
+            let error = ScriptParser::parse(Rule::file, &chunk).err()?;
+            Some(syntax_error(filename, source, error, first_line))
+        })
+        .collect();
+
+    Error { diagnostics }
+}
+
+/// Diagnostic of a part of `source` starting at `first_line`
+fn syntax_error(
+    filename: &Path,
+    source: &str,
+    error: pest::error::Error<Rule>,
+    first_line: usize,
+) -> Diagnostic {
+    let expects_request = matches!(
+        &error.variant,
+        ErrorVariant::ParsingError { positives, .. } if positives.contains(&Rule::method)
+    );
+    let error = error.renamed_rules(|rule| match rule {
+        Rule::method => "request line".to_owned(),
+        Rule::inline_script => "`{{`".to_owned(),
+        Rule::pre_request_handler => "`< {%`".to_owned(),
+        Rule::response_handler => "`> {%`".to_owned(),
+        Rule::directive => "`# @directive`".to_owned(),
+        Rule::request_variable_declaration => "`@name = value`".to_owned(),
+        Rule::request_separator_with_name => "`###`".to_owned(),
+        Rule::import_statement => "`import`".to_owned(),
+        Rule::run_statement => "`run`".to_owned(),
+        Rule::EOI => "end of file".to_owned(),
+        rule => format!("{rule:?}"),
+    });
+
+    let mut selection = error.line_col.clone().to_selection(filename.to_owned());
+    selection.start.line += first_line;
+    selection.end.line += first_line;
+
+    let diagnostic = Diagnostic::new(error.variant.message(), selection, source);
+    if expects_request {
+        return diagnostic.with_hint(
+            "variables and directives must be followed by a request, like `GET https://example.com`",
+        );
+    }
+    diagnostic
+}
+
+/// Parses contents of a file included with `<@ ./path`, so that its `{{ }}` can be substituted
+pub fn parse_template(filename: PathBuf, source: &str) -> Result<Value> {
+    let mut pairs = ScriptParser::parse(Rule::template_file, source).map_err(|error| Error {
+        diagnostics: vec![syntax_error(&filename, source, error, 0)],
+    })?;
+
+    Ok(Value::from_pair(
+        filename,
+        find_rule!(pairs, Rule::template).unwrap(),
+    ))
 }
 
 impl Display for Value {
@@ -374,6 +702,20 @@ impl Unprocessed {
             Unprocessed::WithoutInline(value, _) => value,
         }
     }
+
+    pub fn inline_scripts(&self) -> &[InlineScript] {
+        match self {
+            Unprocessed::WithInline { inline_scripts, .. } => inline_scripts,
+            Unprocessed::WithoutInline(..) => &[],
+        }
+    }
+
+    pub fn selection(&self) -> &Selection {
+        match self {
+            Unprocessed::WithInline { selection, .. } => selection,
+            Unprocessed::WithoutInline(_, selection) => selection,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -381,11 +723,86 @@ pub struct InlineScript {
     pub script: String,
     pub placeholder: String,
     pub selection: Selection,
+    /// `{{raw:name}}`, substituted without escaping
+    pub raw: bool,
+}
+
+impl InlineScript {
+    /// Whether the script is a plain variable name, like `token` or `auth.user`,
+    /// rather than a JavaScript expression
+    pub fn is_variable(&self) -> bool {
+        is_variable_name(&self.script)
+    }
+}
+
+/// Script of `{{raw:script}}`, and whether it had the `raw:` prefix
+pub fn strip_raw(script: &str) -> (&str, bool) {
+    match script.strip_prefix("raw:") {
+        Some(script) => (script.trim_start(), true),
+        None => (script, false),
+    }
+}
+
+/// Variable names are looked up as they are, anything else is evaluated as JavaScript
+pub fn is_variable_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
 }
 
 #[derive(Debug)]
 pub struct File {
     pub request_scripts: Vec<RequestScript>,
+    /// `import ./path.http`, files whose requests can be run by name
+    pub imports: Vec<Import>,
+    /// `run #name` and `run ./path.http`, in the order they appear in the file
+    pub runs: Vec<Run>,
+    /// `# ...` lines outside of bodies and scripts, including directives,
+    /// which are skipped by the grammar and only kept for formatting
+    pub comments: Vec<Comment>,
+    /// Mistakes that do not prevent the file from being executed
+    pub warnings: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub path: String,
+    pub selection: Selection,
+}
+
+#[derive(Debug)]
+pub struct Run {
+    pub target: RunTarget,
+    /// `(@name = value, ...)`, request variables to set for the requests that are run
+    pub overrides: Vec<(String, Value)>,
+    /// Number of requests of the file before this `run`
+    pub position: usize,
+    pub selection: Selection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunTarget {
+    /// `run #name`, a request of the file or of its imports
+    Request(String),
+    /// `run ./path.http`, all requests of another file
+    File(String),
+}
+
+impl Display for RunTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RunTarget::Request(name) => write!(f, "#{name}"),
+            RunTarget::File(path) => f.write_str(path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub selection: Selection,
 }
 
 #[derive(Debug)]
@@ -395,15 +812,66 @@ pub struct RequestScript {
     pub request_variables: Vec<(String, Value)>,
     pub pre_request_handler: Option<Handler>,
     pub handler: Option<Handler>,
+    pub redirect: Option<Redirect>,
+    pub directives: Directives,
     pub selection: Selection,
 }
 
+impl RequestScript {
+    /// `{{...}}` of the request variables, target, headers, body and redirect path,
+    /// in the order they are processed
+    pub fn inline_scripts(&self) -> impl Iterator<Item = &InlineScript> {
+        let mut values = vec![];
+        values.extend(self.request_variables.iter().map(|(_, value)| value));
+        values.push(&self.request.target);
+        values.extend(self.request.headers.iter().map(|it| &it.field_value));
+        match &self.request.body {
+            Some(Body::Inline(value)) => values.push(value),
+            Some(Body::File { path, .. }) => values.push(path),
+            Some(Body::Graphql {
+                query, variables, ..
+            }) => values.extend(std::iter::once(query).chain(variables)),
+            Some(Body::Websocket { steps, .. }) => {
+                values.extend(steps.iter().filter_map(|step| match step {
+                    WebsocketStep::Send(message) => Some(message),
+                    WebsocketStep::WaitForServer => None,
+                }))
+            }
+            None => {}
+        }
+        values.extend(self.redirect.as_ref().map(|it| &it.path));
+
+        values
+            .into_iter()
+            .flat_map(|value| value.state.inline_scripts())
+    }
+}
+
+/// Per-request settings declared with `# @directive` comments
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Directives {
+    /// `# @name <name>`, takes precedence over the name after `###`
+    pub name: Option<String>,
+    /// `# @no-redirect`
+    pub no_redirect: bool,
+    /// `# @timeout <duration>`, left unparsed until the request is executed
+    pub timeout: Option<String>,
+    /// `# @connection-timeout <duration>`
+    pub connection_timeout: Option<String>,
+    /// `# @no-log`
+    pub no_log: bool,
+    /// `# @no-cookie-jar`
+    pub no_cookie_jar: bool,
+    /// `# @max-events <count>`, for streaming responses
+    pub max_events: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct Request {
     pub method: Method,
     pub target: Value,
     pub headers: Vec<Header>,
-    pub body: Option<Value>,
+    pub body: Option<Body>,
     pub selection: Selection,
 }
 
@@ -415,6 +883,137 @@ pub enum Method {
     Put(Selection),
     Patch(Selection),
     Options(Selection),
+    Head(Selection),
+    Trace(Selection),
+    Connect(Selection),
+    /// Sent as `POST` with the query and variables wrapped in a JSON envelope
+    Graphql(Selection),
+    /// Opens a WebSocket connection and exchanges the messages of the body
+    Websocket(Selection),
+    /// Calls `package.Service/Method` with the JSON body translated to protobuf
+    Grpc(Selection),
+    /// Any other method token, e.g. `PROPFIND` or `PURGE`
+    Custom(String, Selection),
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let method = match self {
+            Method::Get(_) => "GET",
+            Method::Post(_) => "POST",
+            Method::Delete(_) => "DELETE",
+            Method::Put(_) => "PUT",
+            Method::Patch(_) => "PATCH",
+            Method::Options(_) => "OPTIONS",
+            Method::Head(_) => "HEAD",
+            Method::Trace(_) => "TRACE",
+            Method::Connect(_) => "CONNECT",
+            Method::Graphql(_) => "GRAPHQL",
+            Method::Websocket(_) => "WEBSOCKET",
+            Method::Grpc(_) => "GRPC",
+            Method::Custom(method, _) => method,
+        };
+        f.write_str(method)
+    }
+}
+
+impl Method {
+    pub fn selection(&self) -> &Selection {
+        match self {
+            Method::Get(selection)
+            | Method::Post(selection)
+            | Method::Delete(selection)
+            | Method::Put(selection)
+            | Method::Patch(selection)
+            | Method::Options(selection)
+            | Method::Head(selection)
+            | Method::Trace(selection)
+            | Method::Connect(selection)
+            | Method::Graphql(selection)
+            | Method::Websocket(selection)
+            | Method::Grpc(selection)
+            | Method::Custom(_, selection) => selection,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Body {
+    Inline(Value),
+    /// `< ./path` or, with variables substituted in the file contents, `<@ ./path`
+    File {
+        path: Value,
+        process_variables: bool,
+        selection: Selection,
+    },
+    /// Body of a `GRAPHQL` request: the query, optionally followed by a JSON object of variables
+    Graphql {
+        query: Value,
+        variables: Option<Value>,
+        selection: Selection,
+    },
+    /// Body of a `WEBSOCKET` request: messages separated by `===` and `=== wait-for-server`
+    Websocket {
+        steps: Vec<WebsocketStep>,
+        selection: Selection,
+    },
+}
+
+impl Body {
+    pub fn selection(&self) -> &Selection {
+        match self {
+            Body::Inline(value) => value.state.selection(),
+            Body::File { selection, .. }
+            | Body::Graphql { selection, .. }
+            | Body::Websocket { selection, .. } => selection,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WebsocketStep {
+    Send(Value),
+    /// `=== wait-for-server`, waits for a message from the server before sending the next one
+    WaitForServer,
+}
+
+impl Display for Body {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Body::Inline(value) => write!(f, "{value}"),
+            Body::File {
+                path,
+                process_variables: true,
+                ..
+            } => write!(f, "<@ {path}"),
+            Body::File { path, .. } => write!(f, "< {path}"),
+            Body::Graphql {
+                query,
+                variables: Some(variables),
+                ..
+            } => write!(f, "{query}\n{variables}"),
+            Body::Graphql { query, .. } => write!(f, "{query}"),
+            Body::Websocket { steps, .. } => {
+                for step in steps {
+                    match step {
+                        WebsocketStep::Send(message) => {
+                            writeln!(f, "===\n{}", message.to_string().trim())?
+                        }
+                        WebsocketStep::WaitForServer => writeln!(f, "=== wait-for-server")?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `>> ./path` saves the response body to a new file, `>>! ./path` overwrites an existing one
+#[derive(Debug)]
+pub struct Redirect {
+    pub path: Value,
+    pub overwrite: bool,
+    pub selection: Selection,
 }
 
 #[derive(Debug)]
@@ -438,6 +1037,14 @@ impl Selection {
             end: Position { line: 0, col: 0 },
         }
     }
+
+    /// Resolves `path` relative to the directory of the file this selection belongs to
+    pub fn resolve_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.filename
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(path)
+    }
 }
 
 impl File {
@@ -445,17 +1052,10 @@ impl File {
         &self,
         request: Option<usize>,
     ) -> impl Iterator<Item = (usize, &RequestScript)> {
-        let mut scripts = self
-            .request_scripts
+        self.request_scripts
             .iter()
             .enumerate()
-            .filter(move |&(index, _)| (request.is_none() || Some(index + 1) == request))
-            .peekable();
-
-        match scripts.peek() {
-            Some(_) => scripts,
-            None => panic!("Couldn't find any scripts in our file at the given line number"),
-        }
+            .filter(move |&(index, _)| request.is_none() || Some(index + 1) == request)
     }
 }
 
@@ -0,0 +1,206 @@
+use color_eyre::eyre::anyhow;
+
+use crate::Result;
+
+/// Single part of a `multipart/form-data` body, as written in a `.http` file:
+///
+/// ```http
+/// --WebAppBoundary
+/// Content-Disposition: form-data; name="data"; filename="data.json"
+/// Content-Type: application/json
+///
+/// < ./request-form-data.json
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    pub headers: Vec<(String, String)>,
+    pub content: Content,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Text(String),
+    /// `< ./path` or, with variables substituted in the file contents, `<@ ./path`
+    File {
+        path: String,
+        process_variables: bool,
+    },
+}
+
+impl Part {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// `name` parameter of the `Content-Disposition` header
+    pub fn name(&self) -> Option<&str> {
+        self.disposition_parameter("name")
+    }
+
+    fn disposition_parameter(&self, parameter: &str) -> Option<&str> {
+        self.header("content-disposition")?
+            .split(';')
+            .skip(1)
+            .filter_map(|it| it.split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(parameter))
+            .map(|(_, value)| value.trim().trim_matches('"'))
+    }
+}
+
+/// Extracts `boundary` out of the `multipart/form-data` content type
+pub fn boundary(content_type: &str) -> Option<&str> {
+    let mut parameters = content_type.split(';');
+    let mime = parameters.next()?.trim();
+    if !mime.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+
+    parameters
+        .filter_map(|it| it.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"'))
+}
+
+pub fn parse(body: &str, boundary: &str) -> Result<Vec<Part>> {
+    let delimiter = format!("--{boundary}");
+    let close_delimiter = format!("--{boundary}--");
+
+    let mut parts = vec![];
+    let mut current: Option<Vec<&str>> = None;
+
+    for line in body.lines() {
+        let trimmed = line.trim_end();
+        if trimmed == delimiter || trimmed == close_delimiter {
+            if let Some(lines) = current.take() {
+                parts.push(parse_part(&lines)?);
+            }
+            if trimmed == close_delimiter {
+                return Ok(parts);
+            }
+            current = Some(vec![]);
+            continue;
+        }
+
+        // anything before the first delimiter is a preamble and is ignored
+        if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+
+    if let Some(lines) = current.take() {
+        parts.push(parse_part(&lines)?);
+    }
+
+    Ok(parts)
+}
+
+fn parse_part(lines: &[&str]) -> Result<Part> {
+    let mut lines = lines.iter();
+    let mut headers = vec![];
+
+    for line in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(anyhow!("Invalid multipart header: `{line}`"));
+        };
+        headers.push((name.trim().to_owned(), value.trim().to_owned()));
+    }
+
+    // line breaks of multipart bodies are CRLF (RFC 2046)
+    let content = lines.copied().collect::<Vec<_>>().join("\r\n");
+    let content = match content.trim() {
+        file if file.contains('\n') => Content::Text(content),
+        file if file.starts_with("<@ ") => Content::File {
+            path: file.strip_prefix("<@ ").unwrap().trim().to_owned(),
+            process_variables: true,
+        },
+        file if file.starts_with("< ") => Content::File {
+            path: file.strip_prefix("< ").unwrap().trim().to_owned(),
+            process_variables: false,
+        },
+        _ => Content::Text(content),
+    };
+
+    Ok(Part { headers, content })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundary_from_content_type() {
+        assert_eq!(
+            boundary("multipart/form-data; boundary=WebAppBoundary"),
+            Some("WebAppBoundary")
+        );
+        assert_eq!(
+            boundary("multipart/form-data; charset=utf-8; boundary=\"quoted\""),
+            Some("quoted")
+        );
+        assert_eq!(boundary("application/json"), None);
+        assert_eq!(boundary("multipart/form-data"), None);
+    }
+
+    #[test]
+    fn parts() {
+        let body = "\
+--WebAppBoundary
+Content-Disposition: form-data; name=\"element-name\"
+Content-Type: text/plain
+
+Name
+--WebAppBoundary
+Content-Disposition: form-data; name=\"data\"; filename=\"data.json\"
+Content-Type: application/json
+
+< ./request-form-data.json
+--WebAppBoundary--
+";
+
+        let parts = parse(body, "WebAppBoundary").unwrap();
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name(), Some("element-name"));
+        assert_eq!(parts[0].header("content-type"), Some("text/plain"));
+        assert_eq!(parts[0].content, Content::Text("Name".to_owned()));
+
+        assert_eq!(parts[1].name(), Some("data"));
+        assert_eq!(
+            parts[1].content,
+            Content::File {
+                path: "./request-form-data.json".to_owned(),
+                process_variables: false
+            }
+        );
+    }
+
+    #[test]
+    fn multiline_content() {
+        let body = "--b\nContent-Disposition: form-data; name=\"notes\"\n\nfirst\nsecond\n--b--\n";
+
+        let parts = parse(body, "b").unwrap();
+
+        assert_eq!(
+            parts[0].content,
+            Content::Text("first\r\nsecond".to_owned())
+        );
+    }
+
+    #[test]
+    fn invalid_header() {
+        let body = "\
+--b
+not a header
+
+value
+--b--";
+
+        assert!(parse(body, "b").is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use super::*;
-use crate::parser;
+use crate::{parser, parser::diagnostic::Severity};
 
 #[test]
 fn script_parser_parse() {
@@ -432,3 +432,509 @@ fn correct_field_value() {
     assert_eq!(inline_scripts[1].script, "y");
     assert_eq!(value, test);
 }
+
+#[test]
+fn arbitrary_methods() {
+    let test = "\
+HEAD http://example.com
+###
+PROPFIND http://example.com/dav
+###
+M-SEARCH http://example.com
+";
+
+    let file = parser::parse(PathBuf::default(), test).unwrap();
+
+    let methods = file
+        .request_scripts
+        .iter()
+        .map(|it| it.request.method.clone())
+        .collect::<Vec<_>>();
+
+    assert!(matches!(methods[0], Method::Head(_)));
+    assert!(matches!(&methods[1], Method::Custom(method, _) if method == "PROPFIND"));
+    assert!(matches!(&methods[2], Method::Custom(method, _) if method == "M-SEARCH"));
+}
+
+#[test]
+fn request_body_from_file() {
+    let test = "\
+POST http://example.com
+Content-Type: application/json
+
+< ./{{dir}}/payload.json
+
+> {%
+    client.log('cool');
+%}
+
+###
+
+POST http://example.com
+
+<@ ./template.json  
+";
+
+    let file = parser::parse(PathBuf::from("requests/test.http"), test).unwrap();
+
+    let Some(Body::File {
+        path,
+        process_variables,
+        selection,
+    }) = &file.request_scripts[0].request.body
+    else {
+        panic!("Expected file body")
+    };
+    assert_eq!(path.to_string(), "./{{dir}}/payload.json");
+    assert!(!process_variables);
+    assert_eq!(
+        selection.resolve_path("./payload.json"),
+        PathBuf::from("requests/./payload.json")
+    );
+    assert!(file.request_scripts[0].handler.is_some());
+
+    let Some(Body::File {
+        path,
+        process_variables,
+        ..
+    }) = &file.request_scripts[1].request.body
+    else {
+        panic!("Expected file body")
+    };
+    assert_eq!(path.to_string(), "./template.json");
+    assert!(process_variables);
+}
+
+#[test]
+fn request_body_starting_with_angle_bracket() {
+    let test = "\
+POST http://example.com
+Content-Type: application/xml
+
+< not a file
+<root/>
+";
+
+    let file = parser::parse(PathBuf::default(), test).unwrap();
+
+    assert!(matches!(
+        file.request_scripts[0].request.body,
+        Some(Body::Inline(_))
+    ));
+}
+
+#[test]
+fn response_redirect() {
+    let test = "\
+GET http://example.com
+
+>> ./out/{{name}}.json
+
+###
+
+POST http://example.com
+
+{ \"a\": 1 }
+
+> {%
+    client.log('cool');
+%}
+
+>>! ./out/post.json
+";
+
+    let file = parser::parse(PathBuf::default(), test).unwrap();
+
+    let first = &file.request_scripts[0];
+    assert!(first.request.body.is_none());
+    let redirect = first.redirect.as_ref().unwrap();
+    assert_eq!(redirect.path.to_string(), "./out/{{name}}.json");
+    assert!(!redirect.overwrite);
+
+    let second = &file.request_scripts[1];
+    let Some(Body::Inline(body)) = &second.request.body else {
+        panic!("Expected inline body")
+    };
+    assert_eq!(body.to_string().trim(), "{ \"a\": 1 }");
+    assert!(second.handler.is_some());
+    let redirect = second.redirect.as_ref().unwrap();
+    assert_eq!(redirect.path.to_string(), "./out/post.json");
+    assert!(redirect.overwrite);
+}
+
+#[test]
+fn request_directives() {
+    let test = "\
+### with directives
+# just a comment
+# @name   named request
+# @no-redirect
+# @timeout 5 s
+# @connection-timeout 500ms
+# @no-log
+# @no-cookie-jar
+# @max-events 10
+# @unknown-directive with value
+GET http://example.com
+# @not-a-directive-here
+
+###
+
+GET http://example.com
+
+###
+
+# @name from directive
+GET http://example.com
+";
+
+    let file = parser::parse(PathBuf::default(), test).unwrap();
+
+    assert_eq!(
+        file.request_scripts[0].directives,
+        Directives {
+            name: Some("named request".to_owned()),
+            no_redirect: true,
+            timeout: Some("5 s".to_owned()),
+            connection_timeout: Some("500ms".to_owned()),
+            no_log: true,
+            no_cookie_jar: true,
+            max_events: Some("10".to_owned()),
+        }
+    );
+    assert_eq!(
+        file.request_scripts[0].name.as_deref(),
+        Some("named request")
+    );
+    assert_eq!(file.request_scripts[1].directives, Directives::default());
+    assert_eq!(file.request_scripts[1].name, None);
+    assert_eq!(
+        file.request_scripts[2].name.as_deref(),
+        Some("from directive")
+    );
+}
+
+#[test]
+fn graphql_request() {
+    let test = "\
+GRAPHQL http://example.com/graphql
+Authorization: Bearer {{token}}
+
+query User($id: ID!) {
+  user(id: $id) {
+
+    name
+  }
+}
+
+{
+  \"id\": \"{{id}}\"
+}
+
+> {%
+    client.log('cool');
+%}
+
+###
+
+GRAPHQL http://example.com/graphql
+
+{ users { name } }
+";
+
+    let file = parser::parse(PathBuf::default(), test).unwrap();
+
+    let request = &file.request_scripts[0].request;
+    assert!(matches!(request.method, Method::Graphql(_)));
+    assert_eq!(request.headers.len(), 1);
+    let Some(Body::Graphql {
+        query,
+        variables: Some(variables),
+        ..
+    }) = &request.body
+    else {
+        panic!("Expected GraphQL body with variables")
+    };
+    assert_eq!(
+        query.to_string().trim(),
+        "query User($id: ID!) {\n  user(id: $id) {\n\n    name\n  }\n}"
+    );
+    assert_eq!(variables.to_string().trim(), "{\n  \"id\": \"{{id}}\"\n}");
+    assert!(file.request_scripts[0].handler.is_some());
+
+    let Some(Body::Graphql {
+        query,
+        variables: None,
+        ..
+    }) = &file.request_scripts[1].request.body
+    else {
+        panic!("Expected GraphQL body without variables")
+    };
+    assert_eq!(query.to_string().trim(), "{ users { name } }");
+}
+
+#[test]
+fn websocket_request() {
+    let test = "\
+WEBSOCKET ws://example.com/ws
+Authorization: Bearer {{token}}
+
+===
+{
+  \"message\": \"{{greeting}}\"
+}
+=== wait-for-server
+=== wait-for-server
+bye
+===
+
+> {%
+    client.log('cool');
+%}
+
+###
+
+WEBSOCKET ws://example.com/ws
+
+hello
+";
+
+    let file = parser::parse(PathBuf::default(), test).unwrap();
+
+    let request = &file.request_scripts[0].request;
+    assert!(matches!(request.method, Method::Websocket(_)));
+    assert_eq!(request.headers.len(), 1);
+    let Some(Body::Websocket { steps, .. }) = &request.body else {
+        panic!("Expected WebSocket body")
+    };
+    assert_eq!(steps.len(), 4);
+    assert!(
+        matches!(&steps[0], WebsocketStep::Send(message) if message.to_string().trim() == "{\n  \"message\": \"{{greeting}}\"\n}")
+    );
+    assert!(matches!(steps[1], WebsocketStep::WaitForServer));
+    assert!(matches!(steps[2], WebsocketStep::WaitForServer));
+    assert!(
+        matches!(&steps[3], WebsocketStep::Send(message) if message.to_string().trim() == "bye")
+    );
+    assert!(file.request_scripts[0].handler.is_some());
+
+    let Some(Body::Websocket { steps, .. }) = &file.request_scripts[1].request.body else {
+        panic!("Expected WebSocket body")
+    };
+    assert!(
+        matches!(&steps[..], [WebsocketStep::Send(message)] if message.to_string().trim() == "hello")
+    );
+}
+
+#[test]
+fn grpc_request() {
+    let test = "\
+GRPC localhost:50051/greeter.Greeter/SayHello
+x-token: {{token}}
+
+{
+  \"name\": \"{{name}}\"
+}
+";
+
+    let file = parser::parse(PathBuf::default(), test).unwrap();
+
+    let request = &file.request_scripts[0].request;
+    assert!(matches!(request.method, Method::Grpc(_)));
+    assert_eq!(
+        request.target.to_string(),
+        "localhost:50051/greeter.Greeter/SayHello"
+    );
+    assert_eq!(request.headers.len(), 1);
+    assert!(matches!(&request.body, Some(Body::Inline(_))));
+}
+
+#[test]
+fn diagnostics() {
+    let test = "\
+GET http://example.com/{{id
+
+###
+POST http://example.com
+Content-Type: application/json
+
+X-Token: 1
+
+{}
+
+> {% client.log(1)
+
+###
+GET http://example.com
+";
+
+    let error = parser::parse(PathBuf::from("test.http"), test).unwrap_err();
+    let error = error.downcast::<parser::Error>().unwrap();
+
+    let diagnostics = error
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let Position { line, col } = diagnostic.selection.start;
+            (diagnostic.message.as_str(), line, col)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        [
+            ("unterminated `{{`", 1, 24),
+            ("`X-Token` header is sent as a part of the body", 7, 1),
+            ("unterminated `{%`", 11, 3),
+        ]
+    );
+    assert_eq!(
+        error.diagnostics[1].hint.as_deref(),
+        Some("header line after body: remove the blank line between the headers and it")
+    );
+    let severities = error
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.severity)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        severities,
+        [Severity::Warning, Severity::Warning, Severity::Error]
+    );
+}
+
+#[test]
+fn missing_target() {
+    let test = "GET http://example.com\n\n###\nPOST \nContent-Type: application/json\n";
+
+    let error = parser::parse(PathBuf::from("test.http"), test).unwrap_err();
+    let error = error.downcast::<parser::Error>().unwrap();
+
+    assert_eq!(error.diagnostics.len(), 1);
+    assert_eq!(error.diagnostics[0].message, "expected a request target");
+    assert_eq!(error.diagnostics[0].selection.start.line, 4);
+}
+
+#[test]
+fn warnings() {
+    let test = "\
+POST http://example.com
+Content-Type: application/yaml
+
+apiVersion: v1
+kind: Pod
+
+spec: {}
+
+###
+POST http://example.com
+
+Hello {{#each items}}, {{ name
+
+###
+POST http://example.com
+
+X-Token: 1
+
+{}
+";
+
+    let file = parser::parse(PathBuf::from("test.http"), test).unwrap();
+
+    let warnings = file
+        .warnings
+        .iter()
+        .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.selection.start.line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            ("unterminated `{{`", 12),
+            ("`X-Token` header is sent as a part of the body", 17)
+        ]
+    );
+}
+
+#[test]
+fn syntax_errors() {
+    let test = "\
+GET http://example.com
+
+###
+@id = 1
+
+###
+GET http://example.com
+
+###
+@name = 2
+";
+
+    let error = parser::parse(PathBuf::from("test.http"), test).unwrap_err();
+    let error = error.downcast::<parser::Error>().unwrap();
+
+    let lines = error
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.selection.start.line)
+        .collect::<Vec<_>>();
+    assert_eq!(lines, [6, 11]);
+    assert!(error.diagnostics[0].message.contains("request line"));
+    assert!(error.diagnostics[0].hint.is_some());
+}
+
+#[test]
+fn missing_request_line() {
+    let test = "\
+@id = 1
+
+###
+GET http://example.com
+";
+
+    let error = parser::parse(PathBuf::from("test.http"), test).unwrap_err();
+    let error = error.downcast::<parser::Error>().unwrap();
+
+    assert_eq!(error.diagnostics.len(), 1);
+    assert_eq!(
+        error.diagnostics[0].message,
+        "expected a request line before `###`"
+    );
+    assert_eq!(error.diagnostics[0].selection.start.line, 3);
+}
+
+#[test]
+fn import_and_run() {
+    let test = "\
+import ./auth.http
+
+run #Login (@user = admin, @password={{password}})
+
+GET http://example.com
+
+###
+run ./cleanup.http
+";
+
+    let file = parser::parse(PathBuf::from("test.http"), test).unwrap();
+
+    assert_eq!(file.request_scripts.len(), 1);
+    assert_eq!(file.imports.len(), 1);
+    assert_eq!(file.imports[0].path, "./auth.http");
+
+    assert_eq!(file.runs.len(), 2);
+    let run = &file.runs[0];
+    assert_eq!(run.target, RunTarget::Request("Login".to_owned()));
+    assert_eq!(run.position, 0);
+    let overrides = run
+        .overrides
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.state.value()))
+        .collect::<Vec<_>>();
+    assert_eq!(overrides, [("user", "admin"), ("password", "{{password}}")]);
+
+    assert_eq!(
+        file.runs[1].target,
+        RunTarget::File("./cleanup.http".to_owned())
+    );
+    assert_eq!(file.runs[1].position, 1);
+}
@@ -3,7 +3,13 @@ mod random;
 mod request;
 mod variables;
 
-use boa_engine::{property::Attribute, Context, JsError, JsValue, Source};
+use std::path::Path;
+
+use boa_engine::{
+    object::builtins::{JsArrayBuffer, JsUint8Array},
+    property::Attribute,
+    Context, JsError, JsValue, Source,
+};
 use client::Client;
 use color_eyre::eyre::{anyhow, Context as _};
 use random::Random;
@@ -17,9 +23,10 @@ use crate::{
             request::{Request, RequestVariables},
             variables::VariableHolder,
         },
+        dynamic::DynamicVariable,
         handle,
         report::TestsReport,
-        Script, ScriptEngine,
+        subscribes_to_messages, Script, ScriptEngine,
     },
     Result,
 };
@@ -27,6 +34,7 @@ use crate::{
 pub struct BoaScriptEngine {
     context: Context<'static>,
     environment: Value,
+    strict_variables: bool,
 }
 
 impl BoaScriptEngine {
@@ -36,6 +44,7 @@ impl BoaScriptEngine {
         let mut engine = BoaScriptEngine {
             context,
             environment,
+            strict_variables: false,
         };
 
         Self::register_client_object(&mut engine.context)?;
@@ -47,6 +56,13 @@ impl BoaScriptEngine {
         Ok(engine)
     }
 
+    /// Fails processing values with variables that are not defined,
+    /// instead of keeping their `{{name}}` as it is
+    pub fn with_strict_variables(mut self, strict_variables: bool) -> Self {
+        self.strict_variables = strict_variables;
+        self
+    }
+
     fn register_global_environment(context: &mut Context, environment: &Value) -> Result<()> {
         Environment::register_holder(context)?;
 
@@ -59,9 +75,8 @@ impl BoaScriptEngine {
                     "Can't register environment value with the name `client`"
                 ));
             }
-            if let Some(value) = v.as_str() {
-                Environment::set_variable(k, value, context)?;
-            }
+            let value = JsValue::from_json(v, context).map_err(map_js_error)?;
+            Environment::set_value(k, value, context)?;
         }
 
         Ok(())
@@ -112,13 +127,27 @@ impl BoaScriptEngine {
 
 impl ScriptEngine for BoaScriptEngine {
     fn execute_script(&mut self, script: &Script) -> Result<String> {
-        execute_script(&mut self.context, script.src)
+        execute_script(&mut self.context, script.src).map_err(|error| {
+            let parser::Selection {
+                filename, start, ..
+            } = &script.selection;
+            // internal scripts are not a part of any file
+            if filename.as_os_str().is_empty() {
+                return error;
+            }
+            anyhow!(
+                "{error}\n  --> {}:{}:{}",
+                filename.display(),
+                start.line,
+                start.col
+            )
+        })
     }
 
     fn reset(&mut self) -> Result<()> {
         let snapshot = self.snapshot()?;
 
-        *self = BoaScriptEngine::new(snapshot)?;
+        *self = BoaScriptEngine::new(snapshot)?.with_strict_variables(self.strict_variables);
         Ok(())
     }
 
@@ -148,18 +177,87 @@ impl ScriptEngine for BoaScriptEngine {
         VariableBlock::set_variable(name, value, &mut self.context)?;
         Ok(())
     }
+    fn define_bytes(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let buffer = JsArrayBuffer::from_byte_block(data.to_vec(), &mut self.context)
+            .map_err(map_js_error)?;
+        let array =
+            JsUint8Array::from_array_buffer(buffer, &mut self.context).map_err(map_js_error)?;
+
+        self.context
+            .global_object()
+            .set(name, array, false, &mut self.context)
+            .map_err(map_js_error)?;
+
+        Ok(())
+    }
     fn pre_handle(&mut self, script: &Script, request: &parser::Request) -> Result<()> {
         Request::register(&mut self.context, request)?;
 
-        self.execute_script(&Script::internal_script(script.src))?;
+        self.execute_script(script)?;
 
         Ok(())
     }
     fn handle(&mut self, request_script: &Script, response: &Response) -> Result<()> {
         handle(self, request_script, response)
     }
-    fn resolve_request_variable(&mut self, name: &str) -> Result<String> {
-        resolve_request_variable(&mut self.context, name)
+    fn subscribes_to_messages(&mut self, script: &Script, response: &Response) -> Result<bool> {
+        let mut engine = BoaScriptEngine::new(self.snapshot()?)?;
+        subscribes_to_messages(&mut engine, script, response)
+    }
+    fn try_resolve_request_variable(&mut self, name: &str, file: &Path) -> Result<Option<String>> {
+        try_resolve_request_variable(&mut self.context, name, file, self.strict_variables)
+    }
+
+    fn strict_variables(&self) -> bool {
+        self.strict_variables
+    }
+
+    fn variable_names(&mut self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for values in [
+            RequestVariables::get_values(&mut self.context)?,
+            VariableBlock::get_values(&mut self.context)?,
+            Environment::get_values(&mut self.context)?,
+        ] {
+            if let Value::Object(values) = JsValue::Object(values)
+                .to_json(&mut self.context)
+                .map_err(map_js_error)?
+            {
+                names.extend(values.into_iter().map(|(name, _)| name));
+            }
+        }
+
+        Ok(names)
+    }
+
+    fn variable(&mut self, name: &str) -> Result<Option<String>> {
+        variable(&mut self.context, name)
+    }
+
+    fn collection(&mut self, name: &str) -> Result<Option<Vec<String>>> {
+        let Some(JsValue::Object(object)) = lookup(&mut self.context, name)? else {
+            return Ok(None);
+        };
+        if !object.is_array() {
+            return Ok(None);
+        }
+
+        let Value::Array(items) = JsValue::Object(object)
+            .to_json(&mut self.context)
+            .map_err(map_js_error)?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            items
+                .into_iter()
+                .map(|item| match item {
+                    Value::String(item) => item,
+                    item => item.to_string(),
+                })
+                .collect(),
+        ))
     }
 }
 
@@ -173,26 +271,101 @@ fn execute_script(ctx: &mut Context, source: &str) -> Result<String> {
     }
 }
 
-fn resolve_request_variable(ctx: &mut Context, name: &str) -> Result<String> {
-    if name.starts_with('$') {
-        return execute_script(ctx, name);
+fn resolve_request_variable(ctx: &mut Context, name: &str, file: &Path) -> Result<String> {
+    // `{{$name}}`
+    Ok(try_resolve_request_variable(ctx, name, file, false)?
+        .unwrap_or_else(|| format!("{{{{{name}}}}}")))
+}
+
+fn try_resolve_request_variable(
+    ctx: &mut Context,
+    name: &str,
+    file: &Path,
+    strict: bool,
+) -> Result<Option<String>> {
+    if let Some(dynamic) = DynamicVariable::parse(name) {
+        return dynamic.resolve(file);
     }
 
-    let mut value = None;
+    if parser::is_variable_name(name) {
+        return variable(ctx, name);
+    }
 
-    value = value.or(RequestVariables::get_variable(name, ctx));
-    value = value.or(VariableBlock::get_variable(name, ctx));
-    value = value.or(Environment::get_variable(name, ctx));
+    evaluate(ctx, name, strict)
+}
 
-    if let Some(value) = value {
-        return value
-            .to_string(ctx)
-            .map(|it| it.to_std_string_escaped())
-            .map_err(map_js_error);
+/// Evaluates a JavaScript expression, like `{{ token.toUpperCase() }}`,
+/// with request, global and environment variables in scope.
+/// An expression that fails is left unresolved, same as an unknown variable, unless `strict`
+fn evaluate(ctx: &mut Context, expression: &str, strict: bool) -> Result<Option<String>> {
+    // innermost scope takes precedence, same as in `lookup`
+    let source = format!(
+        "with (globalThis.{} || {{}}) with (globalThis.{} || {{}}) with (globalThis.{} || {{}}) ({expression}\n)",
+        Environment::NAME,
+        VariableBlock::NAME,
+        RequestVariables::NAME,
+    );
+    let value = match ctx.eval(Source::from_bytes(&source)) {
+        Ok(value) => value,
+        Err(error) if strict => return Err(anyhow!("Error evaluating `{expression}`: {error}")),
+        Err(_) => return Ok(None),
+    };
+
+    if value.is_undefined() {
+        return Ok(None);
     }
+    to_text(ctx, &value).map(Some)
+}
 
-    // `{{$name}}`
-    Ok(format!("{{{{{name}}}}}"))
+fn variable(ctx: &mut Context, name: &str) -> Result<Option<String>> {
+    lookup(ctx, name)?
+        .map(|value| to_text(ctx, &value))
+        .transpose()
+}
+
+/// Looks the variable up in request, global and environment variables, in that order.
+/// Dotted names, like `auth.user` or `items.0`, access properties of object and array values.
+fn lookup(ctx: &mut Context, name: &str) -> Result<Option<JsValue>> {
+    let find = |ctx: &mut Context, name: &str| {
+        RequestVariables::get_variable(name, ctx)
+            .or_else(|| VariableBlock::get_variable(name, ctx))
+            .or_else(|| Environment::get_variable(name, ctx))
+    };
+
+    if let Some(value) = find(ctx, name) {
+        return Ok(Some(value));
+    }
+
+    let mut path = name.split('.');
+    let Some(mut value) = path.next().and_then(|root| find(ctx, root)) else {
+        return Ok(None);
+    };
+    for property in path {
+        let JsValue::Object(object) = value else {
+            return Ok(None);
+        };
+        value = object.get(property, ctx).map_err(map_js_error)?;
+        if value.is_null_or_undefined() {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(value))
+}
+
+/// Strings as they are, objects and arrays as JSON
+fn to_text(ctx: &mut Context, value: &JsValue) -> Result<String> {
+    if let JsValue::Object(object) = value {
+        if !object.is_callable() {
+            let json = value.to_json(ctx).map_err(map_js_error)?;
+            return Ok(json.to_string());
+        }
+    }
+
+    value
+        .to_string(ctx)
+        .map(|it| it.to_std_string_escaped())
+        .map_err(map_js_error)
 }
 
 struct Environment;
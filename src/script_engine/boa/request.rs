@@ -1,4 +1,7 @@
-use std::fmt::Write;
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
 
 use boa_engine::{
     object::ObjectInitializer, property::Attribute, Context, JsNativeError, JsResult, JsValue,
@@ -20,7 +23,7 @@ use crate::{
 ///
 /// interface HttpClientRequest {
 ///     /**
-///      * Information about current request body
+///      * Information about current request body, undefined for a body loaded from a file
 ///      */
 ///     body: RequestBody;
 ///
@@ -52,12 +55,14 @@ impl Request {
     pub fn register(context: &mut Context, request: &parser::Request) -> crate::Result<()> {
         let request_environment = RequestEnvironment::create(context)?;
         let request_variables = RequestVariables::create(context)?;
-        let url = ResolvableValue::create(request.target.state.value(), context)?;
-        let headers = Headers::create(&request.headers, context)?;
-        let body = if let Some(body) = &request.body {
-            ResolvableValue::create(body.state.value(), context)?
-        } else {
-            JsValue::Null
+        let file = &request.selection.filename;
+        let url = ResolvableValue::create(request.target.state.value(), file, context)?;
+        let headers = Headers::create(&request.headers, file, context)?;
+        let body = match &request.body {
+            // the file is only read when the request is sent
+            Some(parser::Body::File { .. }) => JsValue::Undefined,
+            Some(body) => ResolvableValue::create(&body.to_string(), file, context)?,
+            None => JsValue::Null,
         };
 
         let mut obj = ObjectInitializer::new(context);
@@ -86,9 +91,10 @@ impl Request {
 /// interface RequestEnvironment {
 ///     /**
 ///      * Retrieves variable value by its name. Returns null if there is no such variable.
+///      * Values which are not strings in the environment file keep their JSON type.
 ///      * @param name variable name.
 ///      */
-///     get(name: string): string | null
+///     get(name: string): any | null
 /// }
 /// ```
 struct RequestEnvironment;
@@ -129,8 +135,14 @@ impl VariableHolder for RequestVariables {
 }
 
 trait ResolvableInterface {
-    fn initialize(obj: &mut ObjectInitializer, value: &str) {
+    fn initialize(obj: &mut ObjectInitializer, value: &str, file: &Path) {
         obj.property("__resolvable", value, Attribute::default());
+        // request file, for `{{$dotenv NAME}}`
+        obj.property(
+            "__file",
+            file.to_string_lossy().as_ref(),
+            Attribute::default(),
+        );
         obj.function(
             NativeFunction::from_fn_ptr(Self::try_get_substituted),
             "tryGetSubstituted",
@@ -174,6 +186,15 @@ trait ResolvableInterface {
             return Err(error());
         };
 
+        let file = value
+            .get("__file", ctx)
+            .ok()
+            .and_then(|it| {
+                it.as_string()
+                    .map(|it| PathBuf::from(it.to_std_string_escaped()))
+            })
+            .unwrap_or_default();
+
         let value = raw.to_std_string_escaped();
 
         if !value.contains("{{") {
@@ -195,9 +216,9 @@ trait ResolvableInterface {
                         rest = "";
                     }
                     Some(end) => {
-                        let variable = rest[start + 2..end].trim();
+                        let (variable, _) = parser::strip_raw(rest[start + 2..end].trim());
                         rest = &rest[end + 2..];
-                        match resolve_request_variable(ctx, variable) {
+                        match resolve_request_variable(ctx, variable, &file) {
                             Ok(result) => {
                                 write!(resolved, "{result}").unwrap();
                             }
@@ -236,9 +257,9 @@ trait ResolvableInterface {
 pub struct ResolvableValue;
 
 impl ResolvableValue {
-    pub fn create(placeholder: &str, ctx: &mut Context) -> crate::Result<JsValue> {
+    pub fn create(placeholder: &str, file: &Path, ctx: &mut Context) -> crate::Result<JsValue> {
         let mut obj = ObjectInitializer::new(ctx);
-        Self::initialize(&mut obj, placeholder);
+        Self::initialize(&mut obj, placeholder, file);
 
         Ok(JsValue::Object(obj.build()))
     }
@@ -263,11 +284,15 @@ impl ResolvableInterface for ResolvableValue {}
 pub struct Headers;
 
 impl Headers {
-    fn create(headers: &[parser::Header], ctx: &mut Context) -> crate::Result<JsValue> {
+    fn create(
+        headers: &[parser::Header],
+        file: &Path,
+        ctx: &mut Context,
+    ) -> crate::Result<JsValue> {
         let headers: Vec<_> = headers
             .iter()
             .map(|it| {
-                Header::create(&it.field_name, it.field_value.state.value(), ctx)
+                Header::create(&it.field_name, it.field_value.state.value(), file, ctx)
                     .map(|h| (it.field_name.clone(), h))
             })
             .collect::<crate::Result<_>>()?;
@@ -331,9 +356,14 @@ impl Headers {
 pub struct Header;
 
 impl Header {
-    pub fn create(name: &str, value: &str, ctx: &mut Context) -> crate::Result<JsValue> {
+    pub fn create(
+        name: &str,
+        value: &str,
+        file: &Path,
+        ctx: &mut Context,
+    ) -> crate::Result<JsValue> {
         let mut obj = ObjectInitializer::new(ctx);
-        <Self as ResolvableInterface>::initialize(&mut obj, value);
+        <Self as ResolvableInterface>::initialize(&mut obj, value, file);
         obj.property("name", name, Attribute::default());
 
         Ok(JsValue::Object(obj.build()))
@@ -344,6 +374,8 @@ impl ResolvableInterface for Header {}
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use boa_engine::{property::Attribute, Context, Source};
 
     use crate::{
@@ -362,7 +394,8 @@ mod tests {
         let mut ctx = Context::default();
         Environment::register_holder(&mut ctx).unwrap();
 
-        let v = ResolvableValue::create("///{{test}}_{{test}}///", &mut ctx).unwrap();
+        let v =
+            ResolvableValue::create("///{{test}}_{{test}}///", Path::new(""), &mut ctx).unwrap();
         ctx.register_global_property("value", v, Attribute::default())
             .unwrap();
 
@@ -404,6 +437,7 @@ mod tests {
                         script: "test".to_string(),
                         placeholder: "{{test}}".to_string(),
                         selection: Default::default(),
+                        raw: false,
                     }],
                     selection: Default::default(),
                 },
@@ -411,7 +445,7 @@ mod tests {
             selection: parser::Selection::default(),
         }];
 
-        let v = Headers::create(&headers, &mut ctx).unwrap();
+        let v = Headers::create(&headers, Path::new(""), &mut ctx).unwrap();
         ctx.register_global_property("headers", v, Attribute::default())
             .unwrap();
 
@@ -462,7 +496,7 @@ mod tests {
         let mut ctx = Context::default();
         Environment::register_holder(&mut ctx).unwrap();
 
-        let v = ResolvableValue::create("{{ $random.integer }}", &mut ctx).unwrap();
+        let v = ResolvableValue::create("{{ $random.integer }}", Path::new(""), &mut ctx).unwrap();
         ctx.register_global_property("value", v, Attribute::default())
             .unwrap();
 
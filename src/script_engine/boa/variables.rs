@@ -155,6 +155,9 @@ pub(crate) trait VariableHolder {
         Ok(())
     }
     fn set_variable(key: &str, value: &str, ctx: &mut Context) -> crate::Result<()> {
+        Self::set_value(key, value.into(), ctx)
+    }
+    fn set_value(key: &str, value: JsValue, ctx: &mut Context) -> crate::Result<()> {
         let obj = ctx
             .global_object()
             .get(Self::NAME, ctx)
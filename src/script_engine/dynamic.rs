@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use color_eyre::eyre::Context;
+
+use crate::Result;
+
+/// Variables read from outside of the scripts, like `{{$processEnv HOME}}`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DynamicVariable<'a> {
+    /// `$processEnv NAME`, variable of the process environment
+    ProcessEnv(&'a str),
+    /// `$dotenv NAME`, variable of the `.env` file next to the request file
+    Dotenv(&'a str),
+    /// `$env.NAME`, same as `$processEnv NAME`
+    Env(&'a str),
+}
+
+impl<'a> DynamicVariable<'a> {
+    pub fn parse(name: &'a str) -> Option<Self> {
+        if let Some(name) = name.strip_prefix("$env.") {
+            return Some(DynamicVariable::Env(name.trim()));
+        }
+
+        let (kind, name) = name.split_once(char::is_whitespace)?;
+        let name = name.trim();
+        match kind {
+            "$processEnv" => Some(DynamicVariable::ProcessEnv(name)),
+            "$dotenv" => Some(DynamicVariable::Dotenv(name)),
+            _ => None,
+        }
+    }
+
+    /// Value of the variable, for the request in `file`
+    pub fn resolve(&self, file: &Path) -> Result<Option<String>> {
+        match self {
+            DynamicVariable::ProcessEnv(name) | DynamicVariable::Env(name) => {
+                Ok(std::env::var(name).ok())
+            }
+            DynamicVariable::Dotenv(name) => dotenv(file, name),
+        }
+    }
+}
+
+fn dotenv(file: &Path, name: &str) -> Result<Option<String>> {
+    let path = file.with_file_name(".env");
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let items = dotenvy::from_path_iter(&path)
+        .with_context(|| format!("Failed reading {}", path.display()))?;
+    for item in items {
+        let (key, value) = item.with_context(|| format!("Invalid {}", path.display()))?;
+        if key == name {
+            return Ok(Some(value));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS, NON_ALPHANUMERIC};
+
+/// Characters of a path value that are encoded.
+/// Slashes are kept, so that a variable can hold several segments
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Characters of a query or fragment value that are encoded, same as `encodeURIComponent`
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// How values substituted into a `{{ }}` are escaped, depending on where it is used.
+/// `{{raw:name}}` substitutes the value as it is
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Escaping {
+    #[default]
+    Raw,
+    /// Request target: values in the path and the query are percent-encoded,
+    /// while the ones before the path, like `{{host}}`, are substituted as they are
+    Url,
+    /// Values inside JSON strings are escaped
+    Json,
+    /// `application/x-www-form-urlencoded` body
+    Form,
+}
+
+impl Escaping {
+    /// Escaping of a request body with the given content type
+    pub fn from_content_type(content_type: &str) -> Self {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        match mime.as_str() {
+            "application/json" => Escaping::Json,
+            json if json.ends_with("+json") => Escaping::Json,
+            "application/x-www-form-urlencoded" => Escaping::Form,
+            _ => Escaping::Raw,
+        }
+    }
+
+    /// Text around the `{{ }}`
+    pub fn literal<'a>(&self, literal: &'a str) -> Cow<'a, str> {
+        match self {
+            // the target might span several lines
+            Escaping::Url if literal.contains(char::is_whitespace) => literal
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .into(),
+            _ => literal.into(),
+        }
+    }
+
+    /// Escapes `value` substituted after `before`,
+    /// the text preceding it with other `{{ }}` replaced by a placeholder character
+    pub fn escape(&self, before: &str, value: &str) -> String {
+        match self {
+            Escaping::Raw => value.to_owned(),
+            Escaping::Url if before.contains(['?', '#']) => {
+                utf8_percent_encode(value, COMPONENT).to_string()
+            }
+            Escaping::Url => {
+                let (_, rest) = before.split_once("://").unwrap_or(("", before));
+                if rest.contains('/') {
+                    utf8_percent_encode(value, PATH).to_string()
+                } else {
+                    value.to_owned()
+                }
+            }
+            Escaping::Json if in_json_string(before) => {
+                let quoted = serde_json::Value::from(value).to_string();
+                quoted[1..quoted.len() - 1].to_owned()
+            }
+            Escaping::Json => value.to_owned(),
+            Escaping::Form => form_urlencoded::byte_serialize(value.as_bytes()).collect(),
+        }
+    }
+}
+
+/// Whether the end of `json` is inside a string
+fn in_json_string(json: &str) -> bool {
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in json.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ => {}
+        }
+    }
+
+    in_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url() {
+        let url = Escaping::Url;
+
+        assert_eq!(url.escape("http://", "localhost:8080"), "localhost:8080");
+        assert_eq!(
+            url.escape("", "http://localhost/api"),
+            "http://localhost/api"
+        );
+        assert_eq!(url.escape("_/users/", "a b/c?d"), "a%20b/c%3Fd");
+        assert_eq!(url.escape("http://_/get?q=", "a&b=c d"), "a%26b%3Dc%20d");
+        assert_eq!(url.escape("http://_/#", "a b"), "a%20b");
+        assert_eq!(url.literal("http://_/get\n    ?q=1"), "http://_/get?q=1");
+    }
+
+    #[test]
+    fn json() {
+        let json = Escaping::Json;
+
+        assert_eq!(json.escape("{\"token\": \"", "a\"b\\c\n"), "a\\\"b\\\\c\\n");
+        assert_eq!(json.escape("{\"id\": ", "42"), "42");
+        assert_eq!(json.escape("{\"a\": \"\\\"\", \"b\": ", "[1]"), "[1]");
+        assert_eq!(json.escape("{\"a\": \"_\\\\\", \"b\": \"", "\""), "\\\"");
+    }
+
+    #[test]
+    fn form() {
+        assert_eq!(Escaping::Form.escape("name=", "a b&c=d"), "a+b%26c%3Dd");
+    }
+
+    #[test]
+    fn from_content_type() {
+        assert_eq!(
+            Escaping::from_content_type("application/json; charset=utf-8"),
+            Escaping::Json
+        );
+        assert_eq!(
+            Escaping::from_content_type("application/vnd.api+json"),
+            Escaping::Json
+        );
+        assert_eq!(
+            Escaping::from_content_type("application/x-www-form-urlencoded"),
+            Escaping::Form
+        );
+        assert_eq!(Escaping::from_content_type("text/plain"), Escaping::Raw);
+    }
+}
@@ -1,5 +1,6 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, path::Path};
 
+use color_eyre::eyre::anyhow;
 use serde::{Deserialize, Serialize};
 use serde_json::Map;
 
@@ -7,11 +8,13 @@ use crate::{
     environment::EnvironmentProvider,
     http, parser,
     parser::Selection,
-    script_engine::{boa::BoaScriptEngine, report::TestsReport},
+    script_engine::{boa::BoaScriptEngine, escaping::Escaping, report::TestsReport},
     Result,
 };
 
 pub mod boa;
+mod dynamic;
+pub mod escaping;
 pub mod report;
 
 #[cfg(test)]
@@ -39,12 +42,12 @@ pub enum Unprocessed {
 }
 
 #[derive(Debug)]
-
 pub struct InlineScript {
     pub script: String,
     pub placeholder: String,
-    #[allow(unused)]
     pub selection: Selection,
+    /// `{{raw:name}}`, substituted without escaping
+    pub raw: bool,
 }
 
 pub fn create_script_engine(environment: &mut dyn EnvironmentProvider) -> Result<BoaScriptEngine> {
@@ -52,13 +55,12 @@ pub fn create_script_engine(environment: &mut dyn EnvironmentProvider) -> Result
 }
 
 pub struct Script<'a> {
-    #[allow(unused)]
     pub selection: Selection,
     pub src: &'a str,
 }
 
 impl<'a> Script<'a> {
-    pub fn internal_script(src: &str) -> Script {
+    pub fn internal_script(src: &str) -> Script<'_> {
         Script {
             src,
             selection: Selection::none(),
@@ -76,12 +78,42 @@ pub trait ScriptEngine {
     fn report(&mut self) -> Result<TestsReport>;
 
     fn define_variable(&mut self, name: &str, value: &str) -> Result<()>;
+    /// Defines global `Uint8Array` with the given contents
+    fn define_bytes(&mut self, name: &str, data: &[u8]) -> Result<()>;
     fn pre_handle(&mut self, script: &Script, request: &parser::Request) -> Result<()>;
     fn handle(&mut self, script: &Script, response: &http::Response) -> Result<()>;
+    /// Whether the handler subscribes to messages with `onEachMessage` or `onEachLine`, meaning
+    /// it should be run before a stream is read, to receive the messages as they arrive.
+    /// The handler is tried on a copy of the engine, so its effects are discarded.
+    fn subscribes_to_messages(
+        &mut self,
+        script: &Script,
+        response: &http::Response,
+    ) -> Result<bool>;
 
-    fn resolve_request_variable(&mut self, name: &str) -> Result<String>;
+    /// Resolves `{{name}}` of a request in `file`, `None` if the variable is not defined
+    fn try_resolve_request_variable(&mut self, name: &str, file: &Path) -> Result<Option<String>>;
+    /// Whether `process` fails on variables that are not defined
+    fn strict_variables(&self) -> bool;
+    /// Names of request, global and environment variables
+    fn variable_names(&mut self) -> Result<Vec<String>>;
+    /// Value of a request, global or environment variable, if it is defined.
+    /// Dotted names access properties of objects, which are otherwise returned as JSON.
+    fn variable(&mut self, name: &str) -> Result<Option<String>>;
+    /// Elements of a variable holding an array.
+    /// Strings are returned as they are, other values as JSON.
+    fn collection(&mut self, name: &str) -> Result<Option<Vec<String>>>;
 
     fn process(&mut self, value: Value<Unprocessed>) -> Result<Value<Processed>> {
+        self.process_escaped(value, Escaping::Raw)
+    }
+
+    /// Substitutes `{{ }}` of the value, escaping them as `escaping` requires
+    fn process_escaped(
+        &mut self,
+        value: Value<Unprocessed>,
+        escaping: Escaping,
+    ) -> Result<Value<Processed>> {
         match value {
             Value {
                 state:
@@ -91,12 +123,33 @@ pub trait ScriptEngine {
                         selection: _selection,
                     },
             } => {
-                let mut interpolated = value;
+                let mut interpolated = String::with_capacity(value.len());
+                // text preceding the current `{{ }}`, with the previous ones replaced by `_`
+                let mut before = String::new();
+                let mut rest = value.as_str();
                 for inline_script in inline_scripts {
-                    let placeholder = inline_script.placeholder.clone();
-                    let result = self.resolve_request_variable(&inline_script.script)?;
-                    interpolated = interpolated.replacen(placeholder.as_str(), result.as_str(), 1);
+                    let Some(start) = rest.find(&inline_script.placeholder) else {
+                        continue;
+                    };
+                    let literal = &rest[..start];
+                    rest = &rest[start + inline_script.placeholder.len()..];
+                    interpolated.push_str(&escaping.literal(literal));
+                    before.push_str(literal);
+
+                    let file = &inline_script.selection.filename;
+                    match self.try_resolve_request_variable(&inline_script.script, file)? {
+                        Some(result) if inline_script.raw => interpolated.push_str(&result),
+                        Some(result) => interpolated.push_str(&escaping.escape(&before, &result)),
+                        None if self.strict_variables() => {
+                            let names = self.variable_names()?;
+                            return Err(unresolved_variable(&inline_script, &names));
+                        }
+                        // kept as it is, without escaping, so that it is recognizable
+                        None => interpolated.push_str(&format!("{{{{{}}}}}", inline_script.script)),
+                    }
+                    before.push('_');
                 }
+                interpolated.push_str(&escaping.literal(rest));
 
                 Ok(Value {
                     state: Processed {
@@ -107,12 +160,62 @@ pub trait ScriptEngine {
             Value {
                 state: Unprocessed::WithoutInline(value, _),
             } => Ok(Value {
-                state: Processed { value },
+                state: Processed {
+                    value: escaping.literal(&value).into_owned(),
+                },
             }),
         }
     }
 }
 
+/// Error pointing at `{{name}}` in the request file, with the closest known variable name
+fn unresolved_variable(inline_script: &InlineScript, names: &[String]) -> crate::Error {
+    let name = inline_script.script.as_str();
+    let Selection {
+        filename, start, ..
+    } = &inline_script.selection;
+
+    let mut message = format!(
+        "Variable `{name}` is not defined\n  --> {}:{}:{}",
+        filename.display(),
+        start.line,
+        start.col
+    );
+    if let Some(suggestion) = closest(name, names) {
+        message.push_str(&format!("\n  help: did you mean `{suggestion}`?"));
+    }
+
+    anyhow!(message)
+}
+
+/// Name with the smallest edit distance to `name`, if it is close enough to be a typo
+fn closest<'a>(name: &str, names: &'a [String]) -> Option<&'a str> {
+    let limit = name.chars().count().div_ceil(3);
+
+    names
+        .iter()
+        .map(|it| (edit_distance(name, it), it))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, it)| it.as_str())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
 #[derive(Deserialize, Serialize)]
 struct Response {
     body: Option<String>,
@@ -127,7 +230,7 @@ impl From<&http::Response> for Response {
             headers.insert(key.clone(), serde_json::Value::String(value.clone()));
         }
         Response {
-            body: response.body.clone(),
+            body: response.text().map(|it| it.into_owned()),
             headers,
             status: response.status_code,
         }
@@ -140,7 +243,87 @@ fn handle(engine: &mut dyn ScriptEngine, script: &Script, response: &http::Respo
     Ok(())
 }
 
+/// Runs the handler on `engine` without any messages, a handler failing to run this way is
+/// considered not to subscribe
+fn subscribes_to_messages(
+    engine: &mut dyn ScriptEngine,
+    script: &Script,
+    response: &http::Response,
+) -> Result<bool> {
+    engine.execute_script(&Script::internal_script("client.log = function () {};"))?;
+    let response = http::Response {
+        messages: Some(vec![]),
+        ..response.clone()
+    };
+    if handle(engine, script, &response).is_err() {
+        return Ok(false);
+    }
+
+    Ok(engine.execute_script(&Script::internal_script("__subscribed();"))? == "true")
+}
+
+/// Received messages are available as an array, and can be iterated in the ijhttp way with
+/// `response.body.onEachMessage((message, unsubscribe) => ...)`, or `onEachLine` for streams.
+/// Messages of a stream read after the handler are passed to the callbacks with `__receive_message`
+const ON_EACH_MESSAGE: &str = r#"
+{
+    const subscriptions = [];
+    const onEach = function (callback) {
+        const subscription = { callback, subscribed: true };
+        const unsubscribe = () => { subscription.subscribed = false; };
+        subscription.unsubscribe = unsubscribe;
+        for (const message of this) {
+            callback(message, unsubscribe);
+            if (!subscription.subscribed) return;
+        }
+        subscriptions.push(subscription);
+    };
+    Object.defineProperty(response.body, "onEachMessage", { value: onEach });
+    Object.defineProperty(response.body, "onEachLine", { value: onEach });
+
+    globalThis.__subscribed = () => subscriptions.some((it) => it.subscribed);
+    globalThis.__receive_message = function (message) {
+        response.body.push(message);
+        for (const subscription of subscriptions) {
+            if (subscription.subscribed) subscription.callback(message, subscription.unsubscribe);
+        }
+        return __subscribed();
+    };
+}
+"#;
+
+/// Passes a message of a stream to the handler callbacks, returning whether any of them
+/// is still subscribed
+pub(crate) fn receive_message(
+    engine: &mut impl ScriptEngine,
+    message: &http::Message,
+) -> Result<bool> {
+    let script = format!(
+        "__receive_message({});",
+        serde_json::to_string(&message_value(message)).unwrap()
+    );
+    let subscribed = engine.execute_script(&Script::internal_script(&script))?;
+
+    Ok(subscribed == "true")
+}
+
+fn message_value(message: &http::Message) -> serde_json::Value {
+    match message {
+        http::Message::Text(text) => {
+            serde_json::from_str(text).unwrap_or_else(|_| text.as_str().into())
+        }
+        http::Message::Binary(data) => data.as_slice().into(),
+    }
+}
+
 fn inject(engine: &mut dyn ScriptEngine, response: &http::Response) -> Result<()> {
+    let data = response.body.as_deref();
+    let messages = response.messages.as_ref().map(|messages| {
+        messages
+            .iter()
+            .map(message_value)
+            .collect::<Vec<serde_json::Value>>()
+    });
     let response: Response = response.into();
 
     let script = format!(
@@ -148,6 +331,27 @@ fn inject(engine: &mut dyn ScriptEngine, response: &http::Response) -> Result<()
         serde_json::to_string(&response).unwrap()
     );
     engine.execute_script(&Script::internal_script(&script))?;
+
+    if let Some(data) = data {
+        engine.define_bytes("__response_bytes", data)?;
+        // binary bodies are only available as bytes
+        let script = if response.body.is_some() {
+            "response.bytes = __response_bytes; delete globalThis.__response_bytes;"
+        } else {
+            "response.bytes = __response_bytes; delete globalThis.__response_bytes;
+            response.body = response.bytes;"
+        };
+        engine.execute_script(&Script::internal_script(script))?;
+    }
+
+    if let Some(messages) = messages {
+        let script = format!(
+            "response.body = {};\n{ON_EACH_MESSAGE}",
+            serde_json::to_string(&messages).unwrap()
+        );
+        engine.execute_script(&Script::internal_script(&script))?;
+    }
+
     if let Some(body) = response.body {
         if let Ok(serde_json::Value::Object(response_body)) = serde_json::from_str(body.as_str()) {
             let script = format!(
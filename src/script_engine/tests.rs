@@ -1,13 +1,32 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
 use serde_json::json;
 
 use crate::{
-    http::{Response, Version},
+    http::{Message, Response, Version},
+    parser::{self, Position, Selection},
     script_engine::{
         create_script_engine, inject, InlineScript, Script, ScriptEngine, Unprocessed, Value,
     },
     StaticEnvironmentProvider,
 };
 
+trait ResolveRequestVariable {
+    /// Resolves `{{name}}` of a request in `file`, keeping it as it is if it is not defined
+    fn resolve_request_variable(&mut self, name: &str, file: &Path) -> color_eyre::Result<String>;
+}
+
+impl<T: ScriptEngine + ?Sized> ResolveRequestVariable for T {
+    fn resolve_request_variable(&mut self, name: &str, file: &Path) -> color_eyre::Result<String> {
+        Ok(self
+            .try_resolve_request_variable(name, file)?
+            .unwrap_or_else(|| format!("{{{{{name}}}}}")))
+    }
+}
+
 #[test]
 fn test_syntax_error() {
     let mut env = StaticEnvironmentProvider::new(json!({}));
@@ -22,6 +41,27 @@ fn test_syntax_error() {
     );
 }
 
+#[test]
+fn test_error_location() {
+    let mut env = StaticEnvironmentProvider::new(json!({}));
+    let mut engine = create_script_engine(&mut env).unwrap();
+
+    let script = Script {
+        selection: Selection {
+            filename: PathBuf::from("requests.http"),
+            start: Position { line: 3, col: 1 },
+            end: Position { line: 5, col: 3 },
+        },
+        src: "missing();",
+    };
+    let error = engine.execute_script(&script).unwrap_err();
+
+    assert!(
+        error.to_string().ends_with("\n  --> requests.http:3:1"),
+        "{error}"
+    );
+}
+
 #[test]
 fn test_parse_error() {
     let mut env = StaticEnvironmentProvider::new(json!({}));
@@ -49,7 +89,7 @@ fn test_initialize() {
     let mut env = StaticEnvironmentProvider::new(json! ( { "a": "1"} ));
     let mut engine = create_script_engine(&mut env).unwrap();
 
-    let result = engine.resolve_request_variable("a");
+    let result = engine.resolve_request_variable("a", Path::new(""));
 
     assert!(result.is_ok());
 
@@ -71,7 +111,7 @@ fn test_reset() {
 
     engine.reset().unwrap();
 
-    let result = engine.resolve_request_variable("test");
+    let result = engine.resolve_request_variable("test", Path::new(""));
 
     assert!(result.is_ok());
 
@@ -90,7 +130,8 @@ fn test_headers_available_in_response() {
     let response = Response {
         version: Version::Http09,
         headers,
-        body: Some("{}".to_string()),
+        body: Some(b"{}".to_vec()),
+        messages: None,
         status_code: 0,
         status: "".to_string(),
     };
@@ -116,6 +157,7 @@ fn test_special_variables() {
                 script: "$random.integer".to_string(),
                 placeholder: "{{ $random.integer }}".to_string(),
                 selection: Default::default(),
+                raw: false,
             }],
             selection: Default::default(),
         },
@@ -126,3 +168,251 @@ fn test_special_variables() {
     assert!(result.is_ok());
     assert!(result.unwrap().state.value.parse::<i32>().is_ok());
 }
+
+#[test]
+fn test_binary_body_available_as_bytes() {
+    let mut env = StaticEnvironmentProvider::new(json!({}));
+    let mut engine = create_script_engine(&mut env).unwrap();
+
+    let response = Response {
+        version: Version::Http11,
+        headers: vec![(
+            "Content-Type".to_string(),
+            "application/octet-stream".to_string(),
+        )],
+        body: Some(vec![0xde, 0xad, 0xbe, 0xef]),
+        messages: None,
+        status_code: 200,
+        status: "".to_string(),
+    };
+
+    inject(&mut engine, &response).unwrap();
+
+    let result = engine
+        .execute_script(&Script::internal_script(
+            "response.body instanceof Uint8Array && response.body[0] == 0xde && response.bytes.length == 4
+                && typeof __response_bytes == 'undefined'",
+        ))
+        .unwrap();
+
+    assert_eq!("true", result);
+}
+
+#[test]
+fn test_messages_available_in_response() {
+    let mut env = StaticEnvironmentProvider::new(json!({}));
+    let mut engine = create_script_engine(&mut env).unwrap();
+
+    let response = Response {
+        version: Version::Http11,
+        headers: vec![],
+        body: None,
+        messages: Some(vec![
+            Message::Text("{\"count\": 1}".to_string()),
+            Message::Text("plain".to_string()),
+            Message::Text("{\"count\": 3}".to_string()),
+        ]),
+        status_code: 101,
+        status: "".to_string(),
+    };
+
+    inject(&mut engine, &response).unwrap();
+
+    let result = engine
+        .execute_script(&Script::internal_script(
+            "var seen = [];
+            response.body.onEachMessage((message, unsubscribe) => {
+                seen.push(message);
+                if (message === 'plain') unsubscribe();
+            });
+            response.body.length == 3 && response.body[0].count == 1 && seen.length == 2",
+        ))
+        .unwrap();
+
+    assert_eq!("true", result);
+
+    // helpers are defined anew for every response
+    inject(&mut engine, &response).unwrap();
+
+    let result = engine
+        .execute_script(&Script::internal_script(
+            "var lines = [];
+            response.body.onEachLine((line) => lines.push(line));
+            lines.length",
+        ))
+        .unwrap();
+
+    assert_eq!("3", result);
+}
+
+#[test]
+fn test_collection_variables() {
+    let mut env = StaticEnvironmentProvider::new(json!({
+        "ids": [1, "two", { "id": 3 }],
+        "port": 8080,
+    }));
+    let mut engine = create_script_engine(&mut env).unwrap();
+
+    assert_eq!(
+        engine.collection("ids").unwrap(),
+        Some(vec![
+            "1".to_owned(),
+            "two".to_owned(),
+            "{\"id\":3}".to_owned()
+        ])
+    );
+    assert_eq!(engine.collection("port").unwrap(), None);
+    assert_eq!(engine.variable("port").unwrap(), Some("8080".to_owned()));
+
+    engine
+        .execute_script(&Script::internal_script(
+            "client.global.set('tokens', ['a', 'b'])",
+        ))
+        .unwrap();
+    engine.reset().unwrap();
+
+    assert_eq!(
+        engine.collection("tokens").unwrap(),
+        Some(vec!["a".to_owned(), "b".to_owned()])
+    );
+}
+
+#[test]
+fn test_typed_environment_values() {
+    let environment = json!({
+        "auth": { "user": "admin", "roles": ["read", "write"] },
+        "port": 8080,
+        "secure": true,
+    });
+    let mut env = StaticEnvironmentProvider::new(environment.clone());
+    let mut engine = create_script_engine(&mut env).unwrap();
+
+    let resolve = |engine: &mut dyn ScriptEngine, name: &str| {
+        engine
+            .resolve_request_variable(name, Path::new(""))
+            .unwrap()
+    };
+    assert_eq!(resolve(&mut engine, "auth.user"), "admin");
+    assert_eq!(resolve(&mut engine, "auth.roles.1"), "write");
+    assert_eq!(resolve(&mut engine, "auth.roles"), "[\"read\",\"write\"]");
+    assert_eq!(
+        resolve(&mut engine, "auth"),
+        "{\"roles\":[\"read\",\"write\"],\"user\":\"admin\"}"
+    );
+    assert_eq!(resolve(&mut engine, "port"), "8080");
+    assert_eq!(resolve(&mut engine, "secure"), "true");
+    assert_eq!(resolve(&mut engine, "auth.missing"), "{{auth.missing}}");
+
+    let result = engine
+        .execute_script(&Script::internal_script(
+            "client.global.get('auth').user === 'admin' && client.global.get('port') === 8080",
+        ))
+        .unwrap();
+    assert_eq!("true", result);
+
+    engine.reset().unwrap();
+    assert_eq!(engine.snapshot().unwrap(), environment);
+}
+
+#[test]
+fn test_dynamic_variables() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("requests.http");
+    fs::write(
+        directory.path().join(".env"),
+        "# local secrets\nTOKEN=from-dotenv\nQUOTED=\"with spaces\"\n",
+    )
+    .unwrap();
+    std::env::set_var("DOTHTTP_TEST_PROCESS_ENV", "from-process");
+
+    let mut env = StaticEnvironmentProvider::new(json!({}));
+    let mut engine = create_script_engine(&mut env).unwrap();
+
+    let mut resolve = |name: &str| engine.resolve_request_variable(name, &file).unwrap();
+    assert_eq!(
+        resolve("$processEnv DOTHTTP_TEST_PROCESS_ENV"),
+        "from-process"
+    );
+    assert_eq!(resolve("$env.DOTHTTP_TEST_PROCESS_ENV"), "from-process");
+    assert_eq!(resolve("$dotenv TOKEN"), "from-dotenv");
+    assert_eq!(resolve("$dotenv QUOTED"), "with spaces");
+    assert_eq!(resolve("$dotenv MISSING"), "{{$dotenv MISSING}}");
+    assert_eq!(
+        resolve("$processEnv DOTHTTP_TEST_MISSING"),
+        "{{$processEnv DOTHTTP_TEST_MISSING}}"
+    );
+
+    let source = "GET https://example.com?token={{$dotenv TOKEN}}\n";
+    let parsed = parser::parse(file.clone(), source).unwrap();
+    engine
+        .pre_handle(
+            &Script::internal_script("client.global.set('url', request.url.tryGetSubstituted())"),
+            &parsed.request_scripts[0].request,
+        )
+        .unwrap();
+    assert_eq!(
+        engine.variable("url").unwrap().as_deref(),
+        Some("https://example.com?token=from-dotenv")
+    );
+}
+
+#[test]
+fn test_expressions() {
+    let mut env = StaticEnvironmentProvider::new(json!({
+        "token": "secret",
+        "expires": 100,
+        "auth": { "user": "admin" },
+    }));
+    let mut engine = create_script_engine(&mut env).unwrap();
+    engine
+        .execute_script(&Script::internal_script(
+            r#"client.global.set("expires", 200)"#,
+        ))
+        .unwrap();
+
+    let mut resolve = |name: &str| {
+        engine
+            .resolve_request_variable(name, Path::new(""))
+            .unwrap()
+    };
+    assert_eq!(resolve("token.toUpperCase()"), "SECRET");
+    assert_eq!(resolve("expires + 3600"), "3800");
+    assert_eq!(resolve("`${auth.user}:${token}`"), "admin:secret");
+    assert_eq!(resolve("{ user: auth.user }"), "{\"user\":\"admin\"}");
+    assert_eq!(resolve("auth.missing"), "{{auth.missing}}");
+    assert_eq!(resolve("[1, 2].length > 1 ? 'many' : 'one'"), "many");
+    assert_eq!(resolve("Date.now() > 0"), "true");
+
+    assert_eq!(
+        resolve("missing.toUpperCase()"),
+        "{{missing.toUpperCase()}}"
+    );
+    assert_eq!(resolve("a b"), "{{a b}}");
+}
+
+#[test]
+fn test_strict_expressions() {
+    let mut env = StaticEnvironmentProvider::new(json!({ "token": "secret" }));
+    let mut engine = create_script_engine(&mut env)
+        .unwrap()
+        .with_strict_variables(true);
+
+    let error = engine
+        .resolve_request_variable("a b", Path::new(""))
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("Error evaluating `a b`: SyntaxError"),
+        "{error}"
+    );
+    assert!(engine
+        .resolve_request_variable("missing.toUpperCase()", Path::new(""))
+        .is_err());
+    assert_eq!(
+        engine
+            .resolve_request_variable("token.toUpperCase()", Path::new(""))
+            .unwrap(),
+        "SECRET"
+    );
+}
@@ -1,9 +1,14 @@
-use std::{fs, path::Path};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
 
-use color_eyre::eyre::Context;
+use color_eyre::eyre::{anyhow, Context};
 
 use crate::{
-    parser::{parse, File, RequestScript},
+    parser::{
+        self, diagnostic::Diagnostic, parse, File, RequestScript, Run, RunTarget, Selection, Value,
+    },
     Result,
 };
 
@@ -12,6 +17,8 @@ pub struct SourceItem<'a> {
     pub name: &'a str,
     pub index: usize,
     pub script: &'a RequestScript,
+    /// `(@name = value)` of the `run` that executes the request, applied after its own variables
+    pub overrides: &'a [(String, Value)],
 }
 
 impl SourceItem<'_> {
@@ -29,13 +36,24 @@ impl SourceItem<'_> {
 }
 
 pub trait SourceProvider {
-    fn requests(&mut self) -> impl Iterator<Item = SourceItem>;
+    fn requests(&mut self) -> impl Iterator<Item = SourceItem<'_>>;
 }
 
 pub struct FileSourceProvider {
     file: File,
     name: String,
     request_index: Option<usize>,
+    /// Files of the `import`s, in the same order
+    imports: Vec<FileSourceProvider>,
+    /// What each of the `run`s executes, in the same order
+    runs: Vec<RunSource>,
+}
+
+enum RunSource {
+    /// `run #name`, request `index` of the file itself or of one of its imports
+    Request { import: Option<usize>, index: usize },
+    /// `run ./path.http`, every request of another file
+    File(FileSourceProvider),
 }
 
 impl FileSourceProvider {
@@ -45,26 +63,246 @@ impl FileSourceProvider {
         let file_contents = fs::read_to_string(&file)
             .with_context(|| format!("Failed opening script file: `{}`", name))?;
 
-        let file = parse(file.as_ref().to_path_buf(), file_contents.as_str())
+        Self::from_source(file, &file_contents, request_index)
+    }
+
+    /// Requests of `file` with the given contents, e.g. of an unsaved editor buffer
+    pub fn from_source(
+        file: impl AsRef<Path>,
+        file_contents: &str,
+        request_index: Option<usize>,
+    ) -> Result<Self> {
+        Self::load(file.as_ref(), file_contents, request_index, &mut vec![])
+    }
+
+    /// `stack` holds the files importing or running this one, with their names, to detect cycles
+    fn load(
+        path: &Path,
+        file_contents: &str,
+        request_index: Option<usize>,
+        stack: &mut Vec<(PathBuf, String)>,
+    ) -> Result<Self> {
+        let name = path.display().to_string();
+
+        let file = parse(path.to_path_buf(), file_contents)
             .with_context(|| format!("Failed parsing file: `{}`", name))?;
 
+        let has_runs = request_index.is_none() && !file.runs.is_empty();
+        if file.request_scripts(request_index).next().is_none() && !has_runs {
+            return Err(match request_index {
+                Some(index) => anyhow!(
+                    "`{name}` has no request #{index}, the last one is #{}",
+                    file.request_scripts.len()
+                ),
+                None => anyhow!("`{name}` has no requests"),
+            });
+        }
+
+        stack.push((canonical_path(path), name.clone()));
+        let imports = file
+            .imports
+            .iter()
+            .map(|import| {
+                Self::load_relative(&import.selection, &import.path, file_contents, stack)
+            })
+            .collect::<Result<Vec<_>>>();
+        let runs = imports.and_then(|imports| {
+            let runs = file
+                .runs
+                .iter()
+                .map(|run| Self::resolve_run(&file, &imports, run, file_contents, stack))
+                .collect::<Result<Vec<_>>>()?;
+            Ok((imports, runs))
+        });
+        stack.pop();
+        let (imports, runs) = runs?;
+
         Ok(Self {
             file,
             name,
             request_index,
+            imports,
+            runs,
         })
     }
-}
 
-impl SourceProvider for FileSourceProvider {
-    fn requests(&mut self) -> impl Iterator<Item = SourceItem> {
-        self.file
+    /// Loads the file at `path`, which is relative to the file containing `selection`
+    fn load_relative(
+        selection: &Selection,
+        path: &str,
+        source: &str,
+        stack: &mut Vec<(PathBuf, String)>,
+    ) -> Result<Self> {
+        let path = selection
+            .resolve_path(path)
+            .components()
+            .filter(|it| *it != Component::CurDir)
+            .collect::<PathBuf>();
+        let name = path.display().to_string();
+
+        let canonical = canonical_path(&path);
+        if let Some(start) = stack.iter().position(|(it, _)| *it == canonical) {
+            let cycle = stack[start..]
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .chain([name.as_str()])
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(error(
+                Diagnostic::new(format!("import cycle: {cycle}"), selection.clone(), source)
+                    .with_hint(
+                        "a file cannot import or run itself, directly or through other files",
+                    ),
+            ));
+        }
+
+        let file_contents = fs::read_to_string(&path).map_err(|e| {
+            error(Diagnostic::new(
+                format!("cannot read `{name}`: {e}"),
+                selection.clone(),
+                source,
+            ))
+        })?;
+
+        Self::load(&path, &file_contents, None, stack)
+    }
+
+    fn resolve_run(
+        file: &File,
+        imports: &[FileSourceProvider],
+        run: &Run,
+        source: &str,
+        stack: &mut Vec<(PathBuf, String)>,
+    ) -> Result<RunSource> {
+        let name = match &run.target {
+            RunTarget::Request(name) => name,
+            RunTarget::File(path) => {
+                return Self::load_relative(&run.selection, path, source, stack)
+                    .map(RunSource::File)
+            }
+        };
+
+        let find = |file: &File| {
+            file.request_scripts
+                .iter()
+                .position(|script| script.name.as_ref() == Some(name))
+        };
+        if let Some(index) = find(file) {
+            return Ok(RunSource::Request {
+                import: None,
+                index,
+            });
+        }
+
+        imports
+            .iter()
+            .enumerate()
+            .find_map(|(import, provider)| {
+                Some(RunSource::Request {
+                    import: Some(import),
+                    index: find(&provider.file)?,
+                })
+            })
+            .ok_or_else(|| {
+                error(
+                    Diagnostic::new(
+                        format!("no request named `{name}`"),
+                        run.selection.clone(),
+                        source,
+                    )
+                    .with_hint(format!(
+                        "name a request with `### {name}` or `# @name {name}`, in this file or in an imported one"
+                    )),
+                )
+            })
+    }
+
+    /// Requests of the file, with the ones executed by its `run`s in their place
+    fn items(&self) -> Vec<SourceItem<'_>> {
+        let mut requests = self
+            .file
             .request_scripts(self.request_index)
             .map(|(index, script)| SourceItem {
                 name: &self.name,
                 index,
                 script,
-            })
+                overrides: &[],
+            });
+        if self.request_index.is_some() {
+            return requests.collect();
+        }
+
+        let mut items = vec![];
+        let mut position = 0;
+        for (run, source) in self.file.runs.iter().zip(&self.runs) {
+            items.extend(requests.by_ref().take(run.position - position));
+            position = run.position;
+            items.extend(self.run_items(run, source));
+        }
+        items.extend(requests);
+
+        items
+    }
+
+    fn run_items<'a>(&'a self, run: &'a Run, source: &'a RunSource) -> Vec<SourceItem<'a>> {
+        match source {
+            RunSource::Request { import, index } => {
+                let provider = import.map_or(self, |import| &self.imports[import]);
+                vec![SourceItem {
+                    name: &provider.name,
+                    index: *index,
+                    script: &provider.file.request_scripts[*index],
+                    overrides: &run.overrides,
+                }]
+            }
+            RunSource::File(provider) => provider
+                .items()
+                .into_iter()
+                .map(|item| SourceItem {
+                    // the overrides of the nested `run`s are used, unless this one has its own
+                    overrides: if run.overrides.is_empty() {
+                        item.overrides
+                    } else {
+                        &run.overrides
+                    },
+                    ..item
+                })
+                .collect(),
+        }
+    }
+}
+
+fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn error(diagnostic: Diagnostic) -> color_eyre::eyre::Error {
+    parser::Error {
+        diagnostics: vec![diagnostic],
+    }
+    .into()
+}
+
+impl FileSourceProvider {
+    /// Mistakes in the file and the ones it imports or runs, that do not prevent executing them
+    pub fn warnings(&self) -> Vec<&Diagnostic> {
+        let runs = self.runs.iter().filter_map(|run| match run {
+            RunSource::File(provider) => Some(provider),
+            RunSource::Request { .. } => None,
+        });
+
+        self.file
+            .warnings
+            .iter()
+            .chain(self.imports.iter().chain(runs).flat_map(|it| it.warnings()))
+            .collect()
+    }
+}
+
+impl SourceProvider for FileSourceProvider {
+    fn requests(&mut self) -> impl Iterator<Item = SourceItem<'_>> {
+        self.items().into_iter()
     }
 }
 
@@ -90,8 +328,14 @@ impl FilesSourceProvider {
     }
 }
 
+impl FilesSourceProvider {
+    pub fn warnings(&self) -> Vec<&Diagnostic> {
+        self.0.iter().flat_map(|it| it.warnings()).collect()
+    }
+}
+
 impl SourceProvider for FilesSourceProvider {
-    fn requests(&mut self) -> impl Iterator<Item = SourceItem> {
+    fn requests(&mut self) -> impl Iterator<Item = SourceItem<'_>> {
         self.0.iter_mut().flat_map(|it| it.requests())
     }
 }
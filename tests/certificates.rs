@@ -0,0 +1,95 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_rustls::{
+    rustls::{
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+use crate::common::formatter;
+
+#[allow(dead_code)]
+mod common;
+
+#[tokio::test]
+async fn invalid_certificates_are_rejected() {
+    let (addr, handle) = start_https_server().await;
+    let mut output = formatter();
+    let mut environment =
+        StaticEnvironmentProvider::new(json!({ "host": format!("localhost:{}", addr.port()) }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/https.http", None).unwrap())
+        .await;
+    handle.abort();
+
+    let error = result.unwrap_err();
+    assert!(
+        error
+            .chain()
+            .any(|error| error.downcast_ref::<reqwest::Error>().is_some()),
+        "Expected the certificate to be rejected: {error:?}"
+    );
+}
+
+#[tokio::test]
+async fn invalid_certificates_are_accepted_when_asked() {
+    let (addr, handle) = start_https_server().await;
+    let mut output = formatter();
+    let mut environment =
+        StaticEnvironmentProvider::new(json!({ "host": format!("localhost:{}", addr.port()) }));
+    let mut runtime =
+        Runtime::new(&mut environment, &mut output, ClientConfig::new(false)).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/https.http", None).unwrap())
+        .await;
+    handle.abort();
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+}
+
+/// Answers every request with an empty JSON object, using a self-signed certificate of `localhost`
+async fn start_https_server() -> (SocketAddr, tokio::task::JoinHandle<()>) {
+    let certificate = CertificateDer::from_pem_file("tests/certs/localhost.crt").unwrap();
+    let key = PrivateKeyDer::from_pem_file("tests/certs/localhost.key").unwrap();
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate], key)
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    return;
+                };
+                let mut request = vec![];
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(read) => request.extend_from_slice(&buffer[..read]),
+                    }
+                }
+                let response = "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}";
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+
+    (addr, handle)
+}
@@ -1,17 +1,28 @@
 use std::{
-    collections::HashMap, future::IntoFuture, io, io::Write, net::SocketAddr, str::from_utf8,
+    collections::HashMap, convert::Infallible, future::IntoFuture, io, io::Write, net::SocketAddr,
+    str::from_utf8, time::Duration,
 };
 
 use axum::{
-    body::Bytes,
-    extract::Query,
+    body::{Body, Bytes},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        FromRequest, Multipart, Path, Query,
+    },
     http::request::Parts,
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Redirect,
+    },
     routing::{get, post},
     Extension, Router,
 };
 use dothttp::output::{parse_format, print::FormattedOutput};
-use http::header::CONTENT_TYPE;
+use futures_util::{stream, Stream, StreamExt};
+use http::{
+    header::{CONTENT_TYPE, COOKIE, SET_COOKIE},
+    HeaderMap,
+};
 use serde_json::json;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
@@ -54,6 +65,14 @@ impl MockHttpBin {
         let router = Router::new()
             .route("/get", get(mock_get))
             .route("/post", post(mock_post))
+            .route("/multipart", post(mock_multipart))
+            .route("/cookies", get(mock_cookies))
+            .route("/cookies/set", get(mock_set_cookies))
+            .route("/redirect-to", get(mock_redirect_to))
+            .route("/delay/:millis", get(mock_delay))
+            .route("/ws", get(mock_websocket))
+            .route("/sse", get(mock_sse))
+            .route("/ndjson", get(mock_ndjson))
             .layer(Extension(tx));
 
         let listener = tokio::net::TcpListener::bind("0.0.0.0:0").await.unwrap();
@@ -123,6 +142,162 @@ async fn mock_post(
     }))
 }
 
+async fn mock_multipart(
+    Extension(channel): Extension<Sender<(Parts, Bytes)>>,
+    parts: Parts,
+    body: Bytes,
+) -> impl IntoResponse {
+    channel.send((parts.clone(), body.clone())).await.unwrap();
+
+    let request = axum::http::Request::from_parts(parts, Body::from(body));
+    let mut multipart = Multipart::from_request(request, &()).await.unwrap();
+
+    let mut fields = serde_json::Map::new();
+    while let Some(field) = multipart.next_field().await.unwrap() {
+        let name = field.name().unwrap_or_default().to_owned();
+        let filename = field.file_name().map(ToOwned::to_owned);
+        let content_type = field.content_type().map(ToOwned::to_owned);
+        let data = field.bytes().await.unwrap();
+
+        fields.insert(
+            name,
+            json!({
+                "filename": filename,
+                "content_type": content_type,
+                "data": String::from_utf8_lossy(data.as_ref()),
+            }),
+        );
+    }
+
+    axum::Json(json!({ "fields": fields }))
+}
+
+async fn mock_cookies(
+    Extension(channel): Extension<Sender<(Parts, Bytes)>>,
+    parts: Parts,
+) -> impl IntoResponse {
+    channel.send((parts.clone(), Bytes::new())).await.unwrap();
+
+    let cookies: HashMap<_, _> = parts
+        .headers
+        .get_all(COOKIE)
+        .iter()
+        .flat_map(|it| it.to_str().unwrap().split(';'))
+        .filter_map(|it| it.trim().split_once('='))
+        .collect();
+
+    axum::Json(json!({ "cookies": cookies }))
+}
+
+async fn mock_set_cookies(
+    Extension(channel): Extension<Sender<(Parts, Bytes)>>,
+    parts: Parts,
+    Query(args): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    channel.send((parts, Bytes::new())).await.unwrap();
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in args {
+        headers.append(
+            SET_COOKIE,
+            format!("{name}={value}; Path=/; Max-Age=3600")
+                .parse()
+                .unwrap(),
+        );
+    }
+
+    (headers, axum::Json(json!({})))
+}
+
+async fn mock_redirect_to(
+    Extension(channel): Extension<Sender<(Parts, Bytes)>>,
+    parts: Parts,
+    Query(args): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    channel.send((parts, Bytes::new())).await.unwrap();
+
+    Redirect::to(&args["url"])
+}
+
+async fn mock_delay(
+    Extension(channel): Extension<Sender<(Parts, Bytes)>>,
+    parts: Parts,
+    Path(millis): Path<u64>,
+) -> impl IntoResponse {
+    channel.send((parts, Bytes::new())).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(millis)).await;
+
+    axum::Json(json!({}))
+}
+
+/// Replies to every text message with `{"echo": <message>}`, and to `close-me` by closing
+async fn mock_websocket(
+    Extension(channel): Extension<Sender<(Parts, Bytes)>>,
+    parts: Parts,
+    upgrade: WebSocketUpgrade,
+) -> impl IntoResponse {
+    channel.send((parts, Bytes::new())).await.unwrap();
+
+    upgrade.on_upgrade(|mut socket: WebSocket| async move {
+        socket
+            .send(Message::Text(json!({ "greeting": "hello" }).to_string()))
+            .await
+            .unwrap();
+
+        while let Some(Ok(message)) = socket.recv().await {
+            let Message::Text(text) = message else {
+                continue;
+            };
+            if text == "close-me" {
+                break;
+            }
+            let reply = json!({ "echo": text }).to_string();
+            if socket.send(Message::Text(reply)).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Sends `{"id": <n>}` every few milliseconds, `count` times or endlessly
+fn ticks(args: &HashMap<String, String>) -> impl Stream<Item = serde_json::Value> {
+    let count = args.get("count").map(|it| it.parse::<u64>().unwrap());
+
+    stream::unfold(1, move |id| async move {
+        if count.is_some_and(|count| id > count) {
+            return None;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Some((json!({ "id": id }), id + 1))
+    })
+}
+
+async fn mock_sse(
+    Extension(channel): Extension<Sender<(Parts, Bytes)>>,
+    parts: Parts,
+    Query(args): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    channel.send((parts, Bytes::new())).await.unwrap();
+
+    let events = ticks(&args)
+        .map(|tick| Ok::<_, Infallible>(Event::default().event("tick").data(tick.to_string())));
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+async fn mock_ndjson(
+    Extension(channel): Extension<Sender<(Parts, Bytes)>>,
+    parts: Parts,
+    Query(args): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    channel.send((parts, Bytes::new())).await.unwrap();
+
+    let lines = ticks(&args).map(|tick| Ok::<_, Infallible>(format!("{tick}\n")));
+    (
+        [(CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+}
+
 fn collect_headers(parts: &Parts) -> HashMap<String, String> {
     parts
         .headers
@@ -0,0 +1,98 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn cookies_are_shared_between_requests() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()) }),
+    );
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/cookies.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `cookie is sent`: OK")
+            && stdout.0.contains("Test `cookie jar is skipped`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+
+    assert_eq!(server.requests().await.len(), 3);
+}
+
+#[tokio::test]
+async fn cookies_are_persisted() {
+    let mut server = MockHttpBin::start().await;
+    let directory = tempfile::tempdir().unwrap();
+    let cookie_jar = directory.path().join(".cookies.json");
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()) }),
+    );
+
+    let config = ClientConfig {
+        cookie_jar: Some(cookie_jar.clone()),
+        ..ClientConfig::default()
+    };
+    let mut output = formatter();
+    let mut runtime = Runtime::new(&mut environment, &mut output, config).unwrap();
+    runtime
+        .execute(FileSourceProvider::new("tests/requests/cookies.http", Some(1)).unwrap())
+        .await
+        .unwrap();
+
+    assert!(cookie_jar.exists());
+
+    let config = ClientConfig {
+        cookie_jar: Some(cookie_jar.clone()),
+        ..ClientConfig::default()
+    };
+    let mut output = formatter();
+    let mut runtime = Runtime::new(&mut environment, &mut output, config).unwrap();
+    runtime
+        .execute(FileSourceProvider::new("tests/requests/cookies.http", Some(2)).unwrap())
+        .await
+        .unwrap();
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `cookie is sent`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+
+    assert_eq!(server.requests().await.len(), 2);
+}
+
+#[tokio::test]
+async fn empty_cookie_jar_is_not_saved() {
+    let server = MockHttpBin::start().await;
+    let directory = tempfile::tempdir().unwrap();
+    let cookie_jar = directory.path().join(".cookies.json");
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()) }),
+    );
+
+    let config = ClientConfig {
+        cookie_jar: Some(cookie_jar.clone()),
+        ..ClientConfig::default()
+    };
+    let mut output = formatter();
+    let mut runtime = Runtime::new(&mut environment, &mut output, config).unwrap();
+    runtime
+        .execute(FileSourceProvider::new("tests/requests/cookies.http", Some(2)).unwrap())
+        .await
+        .unwrap();
+
+    assert!(!cookie_jar.exists());
+}
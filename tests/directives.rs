@@ -0,0 +1,57 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn directives() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()) }),
+    );
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/directives.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `redirect is not followed`: OK")
+            && stdout.0.contains("Test `redirect is followed`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+    assert!(!stdout.0.contains("quiet"), "Logged:\n{}", stdout.0);
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[3].0.uri, "/get?quiet=true");
+}
+
+#[tokio::test]
+async fn timeout() {
+    let server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()) }),
+    );
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/timeout.http", None).unwrap())
+        .await;
+
+    let error = result.unwrap_err();
+    assert!(
+        error.chain().any(|error| error
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(reqwest::Error::is_timeout)),
+        "Expected a timeout: {error:?}"
+    );
+}
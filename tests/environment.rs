@@ -0,0 +1,305 @@
+use std::{fs, path::Path};
+
+use dothttp::{EnvironmentFileProvider, EnvironmentProvider};
+use serde_json::json;
+
+#[test]
+fn private_environment_overrides_environment() {
+    let directory = tempfile::tempdir().unwrap();
+    let environment = directory.path().join("http-client.env.json");
+    let private_environment = directory.path().join("http-client.private.env.json");
+    let snapshot = directory.path().join(".snapshot.json");
+
+    fs::write(
+        &environment,
+        json!({
+            "dev": { "host": "localhost", "token": "public", "user": "dev" },
+            "prod": { "host": "example.com" },
+        })
+        .to_string(),
+    )
+    .unwrap();
+    fs::write(
+        &private_environment,
+        json!({
+            "dev": { "token": "secret", "password": "hunter2" },
+            "prod": { "token": "prod-secret" },
+        })
+        .to_string(),
+    )
+    .unwrap();
+    fs::write(
+        &snapshot,
+        json!({ "user": "from snapshot", "password": "stale", "saved": "value" }).to_string(),
+    )
+    .unwrap();
+
+    let provider = EnvironmentFileProvider::open(
+        "dev",
+        &environment,
+        EnvironmentFileProvider::private_environment_path(&environment),
+        &snapshot,
+    )
+    .unwrap();
+
+    assert_eq!(
+        provider.snapshot(),
+        json!({
+            "host": "localhost",
+            "token": "secret",
+            "user": "dev",
+            "password": "hunter2",
+            "saved": "value",
+        })
+    );
+}
+
+#[test]
+fn shared_environment_is_overridden_by_selected_one() {
+    let directory = tempfile::tempdir().unwrap();
+    let environment = directory.path().join("http-client.env.json");
+    let private_environment = directory.path().join("http-client.private.env.json");
+
+    fs::write(
+        &environment,
+        json!({
+            "$shared": { "host": "localhost", "version": "v1", "retries": 3 },
+            "dev": { "version": "v2" },
+            "prod": { "host": "example.com" },
+        })
+        .to_string(),
+    )
+    .unwrap();
+    fs::write(
+        &private_environment,
+        json!({
+            "$shared": { "token": "shared-secret", "host": "private.localhost" },
+            "prod": { "token": "prod-secret" },
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let open = |name: &str| {
+        EnvironmentFileProvider::open(
+            name,
+            &environment,
+            &private_environment,
+            directory.path().join(".snapshot.json"),
+        )
+        .unwrap()
+        .snapshot()
+    };
+
+    assert_eq!(
+        open("dev"),
+        json!({
+            "host": "private.localhost",
+            "version": "v2",
+            "retries": 3,
+            "token": "shared-secret",
+        })
+    );
+    assert_eq!(
+        open("prod"),
+        json!({
+            "host": "example.com",
+            "version": "v1",
+            "retries": 3,
+            "token": "prod-secret",
+        })
+    );
+}
+
+#[test]
+fn missing_private_environment_is_empty() {
+    let directory = tempfile::tempdir().unwrap();
+    let environment = directory.path().join("http-client.env.json");
+    fs::write(
+        &environment,
+        json!({ "dev": { "host": "localhost" } }).to_string(),
+    )
+    .unwrap();
+
+    let provider = EnvironmentFileProvider::open(
+        "dev",
+        &environment,
+        directory.path().join("missing.private.env.json"),
+        directory.path().join(".snapshot.json"),
+    )
+    .unwrap();
+
+    assert_eq!(provider.snapshot(), json!({ "host": "localhost" }));
+}
+
+#[test]
+fn private_environment_path() {
+    let path = |it: &str| EnvironmentFileProvider::private_environment_path(it);
+
+    assert_eq!(
+        path("requests/http-client.env.json"),
+        Path::new("requests/http-client.private.env.json")
+    );
+    assert_eq!(path("multi.env.json"), Path::new("multi.private.env.json"));
+    assert_eq!(path("env.json"), Path::new("env.private.json"));
+}
+
+#[test]
+fn snapshots_are_isolated_by_environment() {
+    let directory = tempfile::tempdir().unwrap();
+    let environment = directory.path().join("http-client.env.json");
+    let private_environment = directory.path().join("http-client.private.env.json");
+    let snapshot = directory.path().join(".snapshot.json");
+    fs::write(
+        &environment,
+        json!({ "dev": { "host": "localhost" }, "prod": { "host": "example.com" } }).to_string(),
+    )
+    .unwrap();
+    // snapshot shared by every environment, as it used to be
+    fs::write(&snapshot, json!({ "token": "legacy" }).to_string()).unwrap();
+
+    let open = |name: &str| {
+        EnvironmentFileProvider::open(name, &environment, &private_environment, &snapshot).unwrap()
+    };
+
+    let mut dev = open("dev");
+    assert_eq!(
+        dev.snapshot(),
+        json!({ "host": "localhost", "token": "legacy" })
+    );
+    dev.save(&json!({ "host": "localhost", "token": "dev-token" }))
+        .unwrap();
+
+    let mut prod = open("prod");
+    assert_eq!(prod.snapshot(), json!({ "host": "example.com" }));
+    prod.save(&json!({ "host": "example.com", "token": "prod-token" }))
+        .unwrap();
+
+    assert_eq!(
+        open("dev").snapshot(),
+        json!({ "host": "localhost", "token": "dev-token" })
+    );
+
+    let saved: serde_json::Value = serde_json::from_slice(&fs::read(&snapshot).unwrap()).unwrap();
+    assert_eq!(
+        saved,
+        json!({
+            "$environments": {
+                "dev": { "host": "localhost", "token": "dev-token" },
+                "prod": { "host": "example.com", "token": "prod-token" },
+            }
+        })
+    );
+}
+
+#[test]
+fn variables_override_environment_and_are_not_saved() {
+    let directory = tempfile::tempdir().unwrap();
+    let environment = directory.path().join("http-client.env.json");
+    let snapshot = directory.path().join(".snapshot.json");
+    fs::write(
+        &environment,
+        json!({ "dev": { "host": "localhost", "build": "local" } }).to_string(),
+    )
+    .unwrap();
+
+    let mut provider = EnvironmentFileProvider::open(
+        "dev",
+        &environment,
+        directory.path().join("http-client.private.env.json"),
+        &snapshot,
+    )
+    .unwrap()
+    .with_variables(
+        json!({ "build": "file", "token": "file" })
+            .as_object()
+            .unwrap()
+            .clone(),
+    )
+    .with_variables(json!({ "token": "secret" }).as_object().unwrap().clone());
+
+    assert_eq!(
+        provider.snapshot(),
+        json!({ "host": "localhost", "build": "file", "token": "secret" })
+    );
+
+    // `build` is changed by a script, `token` is not
+    provider
+        .save(&json!({ "host": "localhost", "build": "42", "token": "secret" }))
+        .unwrap();
+
+    let saved: serde_json::Value = serde_json::from_slice(&fs::read(&snapshot).unwrap()).unwrap();
+    assert_eq!(
+        saved,
+        json!({ "$environments": { "dev": { "host": "localhost", "build": "42" } } })
+    );
+}
+
+#[test]
+fn overridden_variables_keep_their_saved_values() {
+    let directory = tempfile::tempdir().unwrap();
+    let environment = directory.path().join("http-client.env.json");
+    let snapshot = directory.path().join(".snapshot.json");
+    fs::write(
+        &snapshot,
+        json!({ "$environments": { "dev": { "session": "abc", "user": "guest" } } }).to_string(),
+    )
+    .unwrap();
+
+    let mut provider = EnvironmentFileProvider::open(
+        "dev",
+        &environment,
+        directory.path().join("http-client.private.env.json"),
+        &snapshot,
+    )
+    .unwrap()
+    .with_variables(
+        json!({ "session": "abc", "user": "admin" })
+            .as_object()
+            .unwrap()
+            .clone(),
+    );
+
+    // the snapshot is saved without changes by scripts
+    provider.save(&provider.snapshot()).unwrap();
+
+    let saved: serde_json::Value = serde_json::from_slice(&fs::read(&snapshot).unwrap()).unwrap();
+    assert_eq!(
+        saved,
+        json!({ "$environments": { "dev": { "session": "abc", "user": "guest" } } })
+    );
+}
+
+#[test]
+fn private_variables_are_not_saved() {
+    let directory = tempfile::tempdir().unwrap();
+    let environment = directory.path().join("http-client.env.json");
+    let private_environment = directory.path().join("http-client.private.env.json");
+    let snapshot = directory.path().join(".snapshot.json");
+    fs::write(
+        &environment,
+        json!({ "dev": { "host": "localhost", "token": "public" } }).to_string(),
+    )
+    .unwrap();
+    fs::write(
+        &private_environment,
+        json!({ "dev": { "token": "secret", "secret": "hunter2", "session": "initial" } })
+            .to_string(),
+    )
+    .unwrap();
+
+    let mut provider =
+        EnvironmentFileProvider::open("dev", &environment, &private_environment, &snapshot)
+            .unwrap();
+
+    // `session` is changed by a script
+    let mut variables = provider.snapshot();
+    variables["session"] = json!("abc");
+    provider.save(&variables).unwrap();
+
+    let saved: serde_json::Value = serde_json::from_slice(&fs::read(&snapshot).unwrap()).unwrap();
+    assert_eq!(
+        saved,
+        json!({ "$environments": { "dev": { "host": "localhost", "token": "public", "session": "abc" } } })
+    );
+}
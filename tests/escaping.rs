@@ -0,0 +1,43 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn values_are_escaped_where_they_are_used() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": format!("127.0.0.1:{}", server.addr.port()),
+        "query": "a&b c",
+        "encoded": "x%20y",
+        "token": "se\"cr\\et",
+        "id": 42,
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/escaping.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 4);
+
+    let (get, _) = &requests[0];
+    assert_eq!(get.uri.path(), "/get");
+    assert_eq!(get.uri.query(), Some("q=a%26b%20c&encoded=x%20y"));
+
+    let (_, json) = &requests[1];
+    let json: serde_json::Value = serde_json::from_slice(json).unwrap();
+    assert_eq!(json, json!({ "token": "se\"cr\\et", "id": 42 }));
+
+    let (_, form) = &requests[2];
+    assert_eq!(form.as_ref(), b"q=a%26b+c&token=se%22cr%5Cet");
+
+    let (unresolved, _) = &requests[3];
+    assert_eq!(unresolved.uri.query(), Some("after={{user}}"));
+}
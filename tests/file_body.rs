@@ -0,0 +1,33 @@
+use std::fs;
+
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn body_from_file() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()), "variable": "42" }),
+    );
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/file-body.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(!stdout.0.contains("FAILED"), "Failed test:\n{}", stdout.0);
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 2);
+
+    let payload = fs::read("tests/requests/payloads/payload.json").unwrap();
+    assert_eq!(requests[0].1, payload);
+}
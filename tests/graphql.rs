@@ -0,0 +1,35 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn graphql() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": format!("127.0.0.1:{}", server.addr.port()),
+        "id": "42",
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/graphql.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `graphql envelope`: OK")
+            && stdout.0.contains("Test `graphql without variables`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|(parts, _)| parts.method == "POST"));
+}
@@ -0,0 +1,314 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use futures_util::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use prost::Message as _;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, Value};
+use serde_json::json;
+use tonic::{
+    body::BoxBody,
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    codegen::{empty_body, http, BoxFuture, Context, Poll, Service},
+    server::{Grpc, NamedService, ServerStreamingService, UnaryService},
+    transport::{Body, Identity, Server, ServerTlsConfig},
+    Request, Response, Status,
+};
+
+use crate::common::formatter;
+
+#[allow(dead_code)]
+mod common;
+
+#[tokio::test]
+async fn grpc_with_proto_files() {
+    let (addr, handle) = start_greeter().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": addr.to_string(),
+        "token": "secret",
+        "name": "dothttp",
+        "proto_files": "./protos/greeter.proto",
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/grpc.http", None).unwrap())
+        .await;
+    handle.abort();
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `unary call`: OK")
+            && stdout.0.contains("Test `error status`: OK")
+            && stdout.0.contains("Test `server streaming`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+}
+
+#[tokio::test]
+async fn grpc_with_server_reflection() {
+    let (addr, handle) = start_greeter().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": addr.to_string(),
+        "token": "secret",
+        "name": "dothttp",
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/grpc.http", Some(1)).unwrap())
+        .await;
+    handle.abort();
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `unary call`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+}
+
+#[tokio::test]
+async fn grpc_with_invalid_certificate() {
+    let (addr, handle) = start_tls_greeter().await;
+    let environment = || {
+        StaticEnvironmentProvider::new(json!({
+            "host": format!("https://localhost:{}", addr.port()),
+            "token": "secret",
+            "name": "dothttp",
+        }))
+    };
+
+    let mut output = formatter();
+    let mut environment_rejecting = environment();
+    let mut runtime = Runtime::new(
+        &mut environment_rejecting,
+        &mut output,
+        ClientConfig::default(),
+    )
+    .unwrap();
+    let rejected = runtime
+        .execute(FileSourceProvider::new("tests/requests/grpc.http", Some(1)).unwrap())
+        .await;
+
+    let mut output = formatter();
+    let mut environment_accepting = environment();
+    let mut runtime = Runtime::new(
+        &mut environment_accepting,
+        &mut output,
+        ClientConfig::new(false),
+    )
+    .unwrap();
+    let accepted = runtime
+        .execute(FileSourceProvider::new("tests/requests/grpc.http", Some(1)).unwrap())
+        .await;
+    handle.abort();
+
+    let error = rejected.unwrap_err();
+    assert!(
+        format!("{error:?}").contains("certificate"),
+        "Expected the certificate to be rejected: {error:?}"
+    );
+    assert!(accepted.is_ok(), "error: {:?}", accepted.unwrap_err());
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `unary call`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+}
+
+async fn start_greeter() -> (SocketAddr, tokio::task::JoinHandle<()>) {
+    start_greeter_with(Server::builder()).await
+}
+
+/// Greeter with a self-signed certificate of `localhost`
+async fn start_tls_greeter() -> (SocketAddr, tokio::task::JoinHandle<()>) {
+    let identity = Identity::from_pem(
+        std::fs::read("tests/certs/localhost.crt").unwrap(),
+        std::fs::read("tests/certs/localhost.key").unwrap(),
+    );
+    let server = Server::builder()
+        .tls_config(ServerTlsConfig::new().identity(identity))
+        .unwrap();
+
+    start_greeter_with(server).await
+}
+
+async fn start_greeter_with(mut server: Server) -> (SocketAddr, tokio::task::JoinHandle<()>) {
+    let descriptors = protox::compile(["greeter.proto"], ["tests/requests/protos"]).unwrap();
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(&descriptors.encode_to_vec())
+        .build()
+        .unwrap();
+    let greeter = Greeter(Arc::new(
+        DescriptorPool::from_file_descriptor_set(descriptors).unwrap(),
+    ));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
+
+    let handle = tokio::spawn(async move {
+        server
+            .add_service(greeter)
+            .add_service(reflection)
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap()
+    });
+
+    (addr, handle)
+}
+
+/// `greeter.Greeter` implemented with dynamic messages, so no code generation is needed
+#[derive(Clone)]
+struct Greeter(Arc<DescriptorPool>);
+
+impl Greeter {
+    fn message(&self, name: &str) -> MessageDescriptor {
+        self.0
+            .get_message_by_name(&format!("greeter.{name}"))
+            .unwrap()
+    }
+}
+
+impl NamedService for Greeter {
+    const NAME: &'static str = "greeter.Greeter";
+}
+
+impl Service<http::Request<Body>> for Greeter {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let greeter = self.clone();
+
+        Box::pin(async move {
+            let response = match request.uri().path() {
+                "/greeter.Greeter/SayHello" => {
+                    let codec = DynamicCodec(greeter.message("HelloRequest"));
+                    Grpc::new(codec).unary(greeter, request).await
+                }
+                "/greeter.Greeter/Count" => {
+                    let codec = DynamicCodec(greeter.message("CountRequest"));
+                    Grpc::new(codec).server_streaming(greeter, request).await
+                }
+                _ => http::Response::builder()
+                    .header("grpc-status", "12")
+                    .header("content-type", "application/grpc")
+                    .body(empty_body())
+                    .unwrap(),
+            };
+
+            Ok(response)
+        })
+    }
+}
+
+impl UnaryService<DynamicMessage> for Greeter {
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<Self::Response>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let reply = self.message("HelloReply");
+
+        Box::pin(async move {
+            if request
+                .metadata()
+                .get("x-token")
+                .and_then(|it| it.to_str().ok())
+                != Some("secret")
+            {
+                return Err(Status::unauthenticated("invalid token"));
+            }
+
+            let name = request.get_ref().get_field_by_name("name").unwrap();
+            let name = name.as_str().unwrap();
+            if name.is_empty() {
+                return Err(Status::invalid_argument("name is required"));
+            }
+
+            let mut message = DynamicMessage::new(reply);
+            message.set_field_by_name("message", Value::String(format!("Hello, {name}!")));
+            message.set_field_by_name("length", Value::I32(name.len() as i32));
+            Ok(Response::new(message))
+        })
+    }
+}
+
+impl ServerStreamingService<DynamicMessage> for Greeter {
+    type Response = DynamicMessage;
+    type ResponseStream = BoxStream<'static, Result<DynamicMessage, Status>>;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let reply = self.message("CountReply");
+
+        Box::pin(async move {
+            let to = request.get_ref().get_field_by_name("to").unwrap();
+            let messages = (1..=to.as_i32().unwrap())
+                .map(|value| {
+                    let mut message = DynamicMessage::new(reply.clone());
+                    message.set_field_by_name("value", Value::I32(value));
+                    message
+                })
+                .collect::<Vec<_>>();
+
+            Ok(Response::new(stream::iter(messages).map(Ok).boxed()))
+        })
+    }
+}
+
+#[derive(Clone)]
+struct DynamicCodec(MessageDescriptor);
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicCodec;
+    type Decoder = DynamicCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        self.clone()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        self.clone()
+    }
+}
+
+impl Encoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst)
+            .map_err(|error| Status::internal(error.to_string()))
+    }
+}
+
+impl Decoder for DynamicCodec {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        DynamicMessage::decode(self.0.clone(), src)
+            .map(Some)
+            .map_err(|error| Status::internal(error.to_string()))
+    }
+}
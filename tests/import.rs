@@ -0,0 +1,61 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::{json, Value};
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn run_imported_requests() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()), "user": "guest" }),
+    );
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/import/import.http", None).unwrap())
+        .await;
+
+    assert!(
+        result.is_ok(),
+        "Failed test:\n{}\nerror: {result:?}",
+        output.into_writers().1 .0
+    );
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 3);
+
+    let user = |body: &[u8]| serde_json::from_slice::<Value>(body).unwrap()["user"].clone();
+    assert_eq!(user(&requests[0].1), "admin");
+    assert_eq!(requests[1].0.uri, "/get?token=admin");
+    assert_eq!(user(&requests[2].1), "guest");
+}
+
+#[test]
+fn import_cycle() {
+    let error = FileSourceProvider::new("tests/requests/import/cycle-a.http", None)
+        .err()
+        .unwrap();
+
+    assert!(
+        error.to_string().starts_with(
+            "error: import cycle: `tests/requests/import/cycle-a.http` -> `tests/requests/import/cycle-b.http` -> `tests/requests/import/cycle-a.http`"
+        ),
+        "{error}"
+    );
+}
+
+#[test]
+fn missing_request() {
+    let error = FileSourceProvider::new("tests/requests/import/missing.http", None)
+        .err()
+        .unwrap();
+
+    assert!(
+        error
+            .to_string()
+            .starts_with("error: no request named `Logout`"),
+        "{error}"
+    );
+}
@@ -0,0 +1,62 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn requests_are_sent_for_every_element() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": format!("127.0.0.1:{}", server.addr.port()),
+        "ids": [1, 2],
+        "languages": ["en", "de"],
+        "filter": [{ "active": true }],
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/iterations.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert_eq!(
+        stdout.0.matches("Test `element is sent`: OK").count(),
+        4,
+        "Output:\n{}",
+        stdout.0
+    );
+    assert_eq!(
+        stdout
+            .0
+            .matches("Test `global element is sent`: OK")
+            .count(),
+        2,
+        "Output:\n{}",
+        stdout.0
+    );
+
+    let requests = server.requests().await;
+    let queries = requests
+        .iter()
+        .filter_map(|(parts, _)| parts.uri.query())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        queries,
+        [
+            "id=1&lang=en",
+            "id=1&lang=de",
+            "id=2&lang=en",
+            "id=2&lang=de"
+        ]
+    );
+    let tokens = requests
+        .iter()
+        .filter_map(|(parts, _)| parts.headers.get("x-token"))
+        .collect::<Vec<_>>();
+    assert_eq!(tokens, ["first", "second"]);
+}
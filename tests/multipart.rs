@@ -0,0 +1,53 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn multipart_body() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": format!("127.0.0.1:{}", server.addr.port()),
+        "variable": "42",
+        "separator": "--WebAppBoundary",
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/multipart.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `parts are sent`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 2);
+
+    let body = String::from_utf8_lossy(&requests[0].1);
+    assert!(
+        body.contains(
+            "--WebAppBoundary\r\n\
+            Content-Disposition: form-data; name=\"notes\"\r\n\
+            \r\n\
+            first line\r\nsecond line\r\n\
+            --WebAppBoundary\r\n"
+        ),
+        "Body:\n{body:?}"
+    );
+
+    // values are substituted after the body is split into parts
+    let body = String::from_utf8_lossy(&requests[1].1);
+    assert!(
+        body.contains("\r\n\r\nbefore\r\n--WebAppBoundary\r\nafter\r\n--WebAppBoundary--"),
+        "Body:\n{body:?}"
+    );
+}
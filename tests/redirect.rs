@@ -0,0 +1,39 @@
+use std::fs;
+
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn response_redirect() {
+    let mut server = MockHttpBin::start().await;
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": format!("127.0.0.1:{}", server.addr.port()),
+        "output": output_dir.path().display().to_string(),
+        "variable": "42",
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/redirect.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+    assert_eq!(server.requests().await.len(), 4);
+
+    let get: serde_json::Value =
+        serde_json::from_slice(&fs::read(output_dir.path().join("get.json")).unwrap()).unwrap();
+    assert_eq!(get["url"], "/get");
+    assert!(output_dir.path().join("get-1.json").exists());
+
+    let post: serde_json::Value = serde_json::from_slice(
+        &fs::read(output_dir.path().join("nested").join("post.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(post["json"]["value"], "overwritten");
+}
@@ -48,3 +48,46 @@ async fn test_simple_post() {
 
     assert_eq!(server.requests().await.len(), 1);
 }
+
+#[tokio::test]
+async fn test_strict_variables() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let host = format!("127.0.0.1:{}", server.addr.port());
+
+    let mut environment = StaticEnvironmentProvider::new(json!({ "host": host, "variable": "42" }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default())
+        .unwrap()
+        .with_strict_variables(true);
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/simple-get.http", Some(1)).unwrap())
+        .await;
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+    assert_eq!(server.requests().await.len(), 1);
+
+    let mut environment = StaticEnvironmentProvider::new(json!({ "host": host, "varable": "42" }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default())
+        .unwrap()
+        .with_strict_variables(true);
+    let error = runtime
+        .execute(FileSourceProvider::new("tests/requests/simple-get.http", Some(1)).unwrap())
+        .await
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        error,
+        "Variable `variable` is not defined\n  --> tests/requests/simple-get.http:3:19\n  help: did you mean `varable`?"
+    );
+}
+
+#[test]
+fn test_missing_request() {
+    let error = FileSourceProvider::new("tests/requests/simple-get.http", Some(2))
+        .err()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        error,
+        "`tests/requests/simple-get.http` has no request #2, the last one is #1"
+    );
+}
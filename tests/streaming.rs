@@ -0,0 +1,53 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn streaming_responses() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": format!("127.0.0.1:{}", server.addr.port()),
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/streaming.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout
+            .0
+            .contains("Test `server-sent events are received`: OK")
+            && stdout.0.contains("Test `events can be iterated`: OK")
+            && stdout.0.contains("Test `json lines are received`: OK")
+            && stdout
+                .0
+                .contains("Test `endless stream stops after max events`: OK")
+            && stdout
+                .0
+                .contains("Test `endless stream stops after timeout`: OK")
+            && stdout
+                .0
+                .contains("Test `handler without callbacks gets all events`: OK")
+            && stdout
+                .0
+                .contains("Test `aliased callbacks get events as they arrive`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+    assert!(
+        stdout.0.contains("<= {\n  \"id\": 1\n}"),
+        "Output:\n{}",
+        stdout.0
+    );
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 7);
+}
@@ -0,0 +1,82 @@
+use dothttp::{source::FileSourceProvider, ClientConfig, Runtime, StaticEnvironmentProvider};
+use serde_json::json;
+
+use crate::common::{formatter, MockHttpBin};
+
+mod common;
+
+#[tokio::test]
+async fn websocket() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(json!({
+        "host": format!("127.0.0.1:{}", server.addr.port()),
+        "token": "secret",
+        "message": "first",
+    }));
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/websocket.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let (stdout, _) = output.into_writers();
+    assert!(
+        stdout.0.contains("Test `messages are received`: OK")
+            && stdout.0.contains("Test `messages can be iterated`: OK"),
+        "Failed test:\n{}",
+        stdout.0
+    );
+    assert!(stdout.0.contains("=> first"), "Output:\n{}", stdout.0);
+    assert!(
+        stdout.0.contains("<= {\n  \"echo\": \"first\"\n}"),
+        "Output:\n{}",
+        stdout.0
+    );
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].0.headers["x-token"], "secret");
+}
+
+#[tokio::test]
+async fn websocket_sends_cookies() {
+    let mut server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()) }),
+    );
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/websocket-cookies.http", None).unwrap())
+        .await;
+
+    assert!(result.is_ok(), "error: {:?}", result.unwrap_err());
+
+    let requests = server.requests().await;
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].0.headers["cookie"], "session=abc");
+}
+
+#[tokio::test]
+async fn websocket_wait_times_out() {
+    let server = MockHttpBin::start().await;
+    let mut output = formatter();
+    let mut environment = StaticEnvironmentProvider::new(
+        json!({ "host": format!("127.0.0.1:{}", server.addr.port()) }),
+    );
+    let mut runtime = Runtime::new(&mut environment, &mut output, ClientConfig::default()).unwrap();
+
+    let result = runtime
+        .execute(FileSourceProvider::new("tests/requests/websocket-timeout.http", None).unwrap())
+        .await;
+
+    let error = result.unwrap_err();
+    assert!(
+        format!("{error:?}").contains("after 200ms"),
+        "Expected a timeout: {error:?}"
+    );
+}